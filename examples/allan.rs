@@ -0,0 +1,76 @@
+//! Records a static capture from `/dev/i2c-N` and prints its Allan deviation and noise terms.
+//!
+//!     cargo run --example allan -- record <bus> <seconds> <log>
+//!     cargo run --example allan -- analyze <log>
+
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use embedded_hal::delay::DelayNs;
+use l3g4200d::allan::{AllanReport, AxisAllan, Capture};
+use l3g4200d::gyro::L3G4200D;
+
+struct ThreadDelay;
+
+impl DelayNs for ThreadDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        thread::sleep(Duration::from_nanos(ns as u64));
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: allan record <bus> <seconds> <log>");
+    eprintln!("       allan analyze <log>");
+    process::exit(2);
+}
+
+fn print_axis(name: &str, axis: &AxisAllan) {
+    let format = |value: Option<f64>| value.map_or_else(|| "-".to_string(), |value| format!("{:.4}", value));
+    println!(
+        "{}: ARW {} °/√h, bias instability {} °/h at {} s, RRW {} °/h/√h",
+        name,
+        format(axis.noise.angle_random_walk_deg_sqrt_h),
+        format(axis.noise.bias_instability_deg_h),
+        format(axis.noise.bias_instability_tau_s),
+        format(axis.noise.rate_random_walk_deg_h_sqrt_h),
+    );
+}
+
+fn print_report(report: &AllanReport) {
+    println!(
+        "{} samples at {} Hz, {} Hz cut-off, ±{} dps",
+        report.samples,
+        report.data_rate.output_data_rate_hz(),
+        report.data_rate.cut_off_hz(),
+        report.full_scale.full_scale_dps(),
+    );
+    println!("tau_s,x_dps,y_dps,z_dps");
+    for ((x, y), z) in report.x.curve.iter().zip(&report.y.curve).zip(&report.z.curve) {
+        println!("{},{},{},{}", x.tau_s, x.deviation, y.deviation, z.deviation);
+    }
+    print_axis("X", &report.x);
+    print_axis("Y", &report.y);
+    print_axis("Z", &report.z);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let capture = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["record", bus, seconds, path] => {
+            let bus: u8 = bus.parse().unwrap_or_else(|_| usage());
+            let seconds: f32 = seconds.parse().unwrap_or_else(|_| usage());
+            let mut gyro = L3G4200D::new(bus);
+            gyro.common_setup().expect("Failed to setup gyro");
+            let samples = (seconds * gyro.data_rate().output_data_rate_hz()) as usize;
+            eprintln!("Recording {} samples, keep the device still", samples);
+            let capture = Capture::record(&mut gyro, &mut ThreadDelay, samples, 100_000).expect("Failed to record");
+            capture.save(path).expect("Failed to save capture");
+            capture
+        }
+        ["analyze", path] => Capture::load(path).expect("Failed to load capture"),
+        _ => usage(),
+    };
+    print_report(&capture.analyze(10));
+}
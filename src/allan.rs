@@ -0,0 +1,409 @@
+#[cfg(feature = "std")]
+use std::fmt::Write;
+
+#[cfg(feature = "std")]
+use embedded_hal::delay::DelayNs;
+use libm::{exp, log, pow, round, sqrt};
+#[cfg(feature = "std")]
+use thiserror::Error;
+
+#[cfg(feature = "std")]
+use crate::config::ctrlreg1::{self, DataRateBandwidth};
+#[cfg(feature = "std")]
+use crate::config::ctrlreg4::{self, FullScaleSelection};
+#[cfg(feature = "std")]
+use crate::errors::L3G4200DError;
+use crate::fusion::kalman::NoiseParameters;
+#[cfg(feature = "std")]
+use crate::gyro::L3G4200D;
+#[cfg(feature = "std")]
+use crate::interface::Interface;
+
+/// Ratio of the flat bottom of the Allan deviation to the bias instability of flicker noise,
+/// √(2 ln 2 / π).
+pub const BIAS_INSTABILITY_FACTOR: f64 = 0.664;
+
+// Largest distance to -1/2 or +1/2 for a slope of the curve to count as white or random walk noise
+const SLOPE_TOLERANCE: f64 = 0.25;
+
+/// Overlapping Allan deviation of `rates` (in any unit, e.g. dps) over clusters of `cluster`
+/// samples, i.e. at τ = cluster / sample rate. `None` unless the data holds at least two
+/// overlapping pairs of clusters.
+pub fn overlapping_allan_deviation(rates: &[f64], cluster: usize) -> Option<f64> {
+    let m = cluster;
+    if m == 0 || rates.len() < 2 * m + 1 {
+        return None;
+    }
+    // Sliding sums of two neighbouring clusters, so that every τ takes a single pass
+    let mut first: f64 = rates[..m].iter().sum();
+    let mut second: f64 = rates[m..2 * m].iter().sum();
+    let terms = rates.len() - 2 * m + 1;
+    let mut sum = 0.0;
+    for k in 0..terms {
+        let difference = (second - first) / m as f64;
+        sum += difference * difference;
+        if k + 1 < terms {
+            first += rates[k + m] - rates[k];
+            second += rates[k + 2 * m] - rates[k + m];
+        }
+    }
+    Some(sqrt(sum / (2.0 * terms as f64)))
+}
+
+/// Cluster sizes spaced evenly on a log scale, `per_decade` per decade, from 1 sample up to the
+/// largest one `overlapping_allan_deviation` accepts for `samples` samples.
+#[derive(Debug, Clone)]
+pub struct ClusterSizes {
+    per_decade: u32,
+    step: u32,
+    previous: usize,
+    largest: usize,
+}
+
+impl ClusterSizes {
+    pub fn new(samples: usize, per_decade: u32) -> Self {
+        ClusterSizes { per_decade: per_decade.max(1), step: 0, previous: 0, largest: samples.saturating_sub(1) / 2 }
+    }
+}
+
+impl Iterator for ClusterSizes {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            let size = round(pow(10.0, self.step as f64 / self.per_decade as f64)) as usize;
+            self.step += 1;
+            if size > self.largest {
+                return None;
+            }
+            if size > self.previous {
+                self.previous = size;
+                return Some(size);
+            }
+        }
+    }
+}
+
+/// One point of an Allan deviation curve.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AllanPoint {
+    /// Averaging time, in seconds
+    pub tau_s: f64,
+    /// In the unit of the rates, dps for the driver
+    pub deviation: f64,
+}
+
+/// Gyroscope noise read from an Allan deviation curve in dps, in the units of IEEE Std 952.
+///
+/// Each term is `None` when the curve does not show it: no part with a slope near -1/2 for the
+/// angle random walk, no minimum before the last point for the bias instability (the capture is
+/// too short), or no part with a slope near +1/2 for the rate random walk.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct NoiseCharacteristics {
+    /// Angle random walk, in °/√h: the curve's -1/2 slope line at τ = 1 s, times 60
+    pub angle_random_walk_deg_sqrt_h: Option<f64>,
+    /// Bias instability, in °/h: the curve's minimum divided by `BIAS_INSTABILITY_FACTOR`
+    pub bias_instability_deg_h: Option<f64>,
+    /// Averaging time of the curve's minimum, in seconds
+    pub bias_instability_tau_s: Option<f64>,
+    /// Rate random walk, in °/h/√h: the curve's +1/2 slope line at τ = 3 s
+    pub rate_random_walk_deg_h_sqrt_h: Option<f64>,
+}
+
+// Where the log-log slope of `curve` is closest to `slope`, the value at `tau` of the line with
+// that slope through the two points
+fn slope_line_at(curve: &[AllanPoint], slope: f64, tau: f64) -> Option<f64> {
+    let (offset, distance) = curve.windows(2)
+        .filter(|pair| pair[0].deviation > 0.0 && pair[1].deviation > 0.0)
+        .map(|pair| {
+            let local = log(pair[1].deviation / pair[0].deviation) / log(pair[1].tau_s / pair[0].tau_s);
+            let offset = pair.iter().map(|point| log(point.deviation) - slope * log(point.tau_s)).sum::<f64>() / 2.0;
+            (offset, (local - slope).abs())
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+    (distance <= SLOPE_TOLERANCE).then(|| exp(offset + slope * log(tau)))
+}
+
+impl NoiseCharacteristics {
+    /// Reads the noise terms from a curve in dps, sorted by averaging time.
+    pub fn from_curve(curve: &[AllanPoint]) -> Self {
+        let minimum = curve.iter().enumerate()
+            .filter(|(_, point)| point.deviation > 0.0)
+            .min_by(|a, b| a.1.deviation.total_cmp(&b.1.deviation))
+            .filter(|(index, _)| index + 1 < curve.len())
+            .map(|(_, point)| point);
+        NoiseCharacteristics {
+            angle_random_walk_deg_sqrt_h: slope_line_at(curve, -0.5, 1.0).map(|value| value * 60.0),
+            bias_instability_deg_h: minimum.map(|point| point.deviation / BIAS_INSTABILITY_FACTOR * 3600.0),
+            bias_instability_tau_s: minimum.map(|point| point.tau_s),
+            rate_random_walk_deg_h_sqrt_h: slope_line_at(curve, 0.5, 3.0).map(|value| value * 3600.0 * 60.0),
+        }
+    }
+
+    /// White rate noise density in dps/√Hz, as given by the datasheet.
+    pub fn noise_density_dps_sqrt_hz(&self) -> Option<f64> {
+        self.angle_random_walk_deg_sqrt_h.map(|value| value / 60.0)
+    }
+
+    /// Process noise for `fusion::kalman`, when both the angle random walk and the bias
+    /// instability were found. The bias wanders over the averaging time of the curve's minimum.
+    pub fn noise_parameters(&self) -> Option<NoiseParameters> {
+        Some(NoiseParameters {
+            noise_density_dps_sqrt_hz: self.noise_density_dps_sqrt_hz()?,
+            bias_instability_dps: self.bias_instability_deg_h? / 3600.0,
+            bias_correlation_time_s: self.bias_instability_tau_s?,
+        })
+    }
+}
+
+/// Static recording of the rates in dps, with the settings it was taken with.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Debug, Clone)]
+pub struct Capture {
+    pub data_rate: DataRateBandwidth,
+    pub full_scale: FullScaleSelection,
+    pub rates_dps: Vec<[f32; 3]>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Failed to access capture file")]
+    Io(std::io::Error),
+    #[error("Malformed capture log at line {0}")]
+    Format(usize),
+    #[error("Capture log does not give the data rate and full scale")]
+    MissingSettings(),
+}
+
+/// Allan deviation and noise terms of one axis.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Debug, Clone)]
+pub struct AxisAllan {
+    pub curve: Vec<AllanPoint>,
+    pub noise: NoiseCharacteristics,
+}
+
+/// Result of `Capture::analyze`, with the settings the noise was measured with.
+#[cfg(feature = "std")]
+#[derive(PartialEq, Debug, Clone)]
+pub struct AllanReport {
+    pub data_rate: DataRateBandwidth,
+    pub full_scale: FullScaleSelection,
+    pub samples: usize,
+    pub x: AxisAllan,
+    pub y: AxisAllan,
+    pub z: AxisAllan,
+}
+
+#[cfg(feature = "std")]
+impl Capture {
+    /// Records `samples` fresh rates from `gyro`, waiting for each one through STATUS_REG, with
+    /// the data rate and full scale the driver has set. Keep the device still: hours of data are
+    /// needed to see the bias instability and the rate random walk.
+    pub fn record<DI: Interface, D: DelayNs>(gyro: &mut L3G4200D<DI>, delay: &mut D, samples: usize, timeout_us: u32) -> Result<Self, L3G4200DError<DI::Error>> {
+        let mut rates_dps = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            gyro.wait_for_data_ready(delay, timeout_us)?;
+            let (x, y, z) = gyro.read_rate_dps()?;
+            rates_dps.push([x, y, z]);
+        }
+        Ok(Capture { data_rate: gyro.data_rate(), full_scale: gyro.full_scale(), rates_dps })
+    }
+
+    pub fn sample_rate_hz(&self) -> f64 {
+        self.data_rate.output_data_rate_hz() as f64
+    }
+
+    /// Text log: a header line with the settings, then one `x,y,z` line in dps per sample. The
+    /// header holds the DR/BW bits of CTRL_REG1 and the FS bits of CTRL_REG4 as they were set,
+    /// followed by what they mean.
+    pub fn to_log(&self) -> String {
+        let ctrl_reg1 = ctrlreg1::Value {
+            dr_bw: self.data_rate,
+            power_down_mode_enable: ctrlreg1::PowerDownMode::POWER_DOWN_MODE_ENABLE,
+            x_enable: false,
+            y_enable: false,
+            z_enable: false,
+        };
+        let ctrl_reg4 = ctrlreg4::Value { full_scale_select: self.full_scale, ..Default::default() };
+        let mut log = format!(
+            "# dr_bw={:#04x} fs={:#04x} odr_hz={} cut_off_hz={} full_scale_dps={}\n",
+            ctrl_reg1.to_value(),
+            ctrl_reg4.to_value(),
+            self.data_rate.output_data_rate_hz(),
+            self.data_rate.cut_off_hz(),
+            self.full_scale.full_scale_dps(),
+        );
+        for [x, y, z] in &self.rates_dps {
+            let _ = writeln!(log, "{},{},{}", x, y, z);
+        }
+        log
+    }
+
+    /// Reads a log written by `to_log`. Empty lines and other `#` lines are skipped.
+    pub fn from_log(log: &str) -> Result<Self, CaptureError> {
+        let (mut data_rate, mut full_scale) = (None, None);
+        let mut rates_dps = Vec::new();
+        for (index, line) in log.lines().enumerate() {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix('#') {
+                for (key, value) in header.split_whitespace().filter_map(|field| field.split_once('=')) {
+                    let bits = || {
+                        let digits = value.strip_prefix("0x").ok_or(CaptureError::Format(index + 1))?;
+                        u8::from_str_radix(digits, 16).map_err(|_| CaptureError::Format(index + 1))
+                    };
+                    match key {
+                        "dr_bw" => data_rate = Some(ctrlreg1::Value::from(bits()?).dr_bw),
+                        "fs" => full_scale = Some(ctrlreg4::Value::from(bits()?).full_scale_select),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let mut values = line.split(',').map(|value| value.trim().parse::<f32>());
+            match (values.next(), values.next(), values.next(), values.next()) {
+                (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => rates_dps.push([x, y, z]),
+                _ => return Err(CaptureError::Format(index + 1)),
+            }
+        }
+        match (data_rate, full_scale) {
+            (Some(data_rate), Some(full_scale)) => Ok(Capture { data_rate, full_scale, rates_dps }),
+            _ => Err(CaptureError::MissingSettings()),
+        }
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), CaptureError> {
+        std::fs::write(path, self.to_log()).map_err(CaptureError::Io)
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, CaptureError> {
+        let log = std::fs::read_to_string(path).map_err(CaptureError::Io)?;
+        Self::from_log(&log)
+    }
+
+    /// Overlapping Allan deviation of each axis at `per_decade` averaging times per decade, and
+    /// the noise terms read from it.
+    pub fn analyze(&self, per_decade: u32) -> AllanReport {
+        let sample_rate_hz = self.sample_rate_hz();
+        let axis = |index: usize| {
+            let rates: Vec<f64> = self.rates_dps.iter().map(|rate| rate[index] as f64).collect();
+            let curve: Vec<AllanPoint> = ClusterSizes::new(rates.len(), per_decade)
+                .filter_map(|cluster| {
+                    let deviation = overlapping_allan_deviation(&rates, cluster)?;
+                    Some(AllanPoint { tau_s: cluster as f64 / sample_rate_hz, deviation })
+                })
+                .collect();
+            let noise = NoiseCharacteristics::from_curve(&curve);
+            AxisAllan { curve, noise }
+        };
+        AllanReport {
+            data_rate: self.data_rate,
+            full_scale: self.full_scale,
+            samples: self.rates_dps.len(),
+            x: axis(0),
+            y: axis(1),
+            z: axis(2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Gaussian-ish noise (sum of 12 uniforms) with unit standard deviation, repeatable
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f64 {
+            (0..12).map(|_| {
+                self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                (self.0 >> 11) as f64 / (1u64 << 53) as f64
+            }).sum::<f64>() - 6.0
+        }
+    }
+
+    fn curve(rates: &[f64], sample_rate_hz: f64) -> Vec<AllanPoint> {
+        ClusterSizes::new(rates.len(), 10)
+            .filter_map(|cluster| Some(AllanPoint { tau_s: cluster as f64 / sample_rate_hz, deviation: overlapping_allan_deviation(rates, cluster)? }))
+            .collect()
+    }
+
+    #[test]
+    fn deviation_of_known_sequences() {
+        assert_eq!(overlapping_allan_deviation(&[2.0; 10], 3), Some(0.0));
+        // Steps of ±1 between neighbouring samples: every difference is 1
+        let alternating: Vec<f64> = (0..9).map(|i| (i % 2) as f64).collect();
+        assert!((overlapping_allan_deviation(&alternating, 1).unwrap() - sqrt(0.5)).abs() < 1e-12);
+        assert_eq!(overlapping_allan_deviation(&[1.0, 2.0, 3.0, 4.0], 2), None);
+        assert_eq!(overlapping_allan_deviation(&[1.0; 4], 0), None);
+    }
+
+    #[test]
+    fn cluster_sizes_are_log_spaced() {
+        let sizes: Vec<usize> = ClusterSizes::new(1000, 4).collect();
+        assert_eq!(sizes, [1, 2, 3, 6, 10, 18, 32, 56, 100, 178, 316]);
+        assert_eq!(ClusterSizes::new(2, 10).count(), 0);
+    }
+
+    #[test]
+    fn white_noise_gives_angle_random_walk() {
+        // 0.03 dps/√Hz at 100 Hz is 0.3 dps per sample
+        let mut noise = Noise(1);
+        let rates: Vec<f64> = (0..200_000).map(|_| 0.3 * noise.next()).collect();
+        let characteristics = NoiseCharacteristics::from_curve(&curve(&rates, 100.0));
+        let arw = characteristics.angle_random_walk_deg_sqrt_h.unwrap();
+        assert!((arw - 1.8).abs() < 0.1, "{}", arw);
+        // White noise alone keeps going down, there is no floor yet
+        assert_eq!(characteristics.bias_instability_deg_h, None);
+        assert_eq!(characteristics.noise_parameters(), None);
+    }
+
+    #[test]
+    fn random_walk_gives_a_floor_and_rate_random_walk() {
+        // White noise plus a bias random walk of 0.002 dps/√s
+        let mut noise = Noise(2);
+        let mut bias = 0.0;
+        let rates: Vec<f64> = (0..400_000).map(|_| {
+            bias += 0.002 * sqrt(0.01) * noise.next();
+            0.3 * noise.next() + bias
+        }).collect();
+        let characteristics = NoiseCharacteristics::from_curve(&curve(&rates, 100.0));
+        let rrw = characteristics.rate_random_walk_deg_h_sqrt_h.unwrap();
+        assert!((rrw / 216_000.0 - 0.002).abs() < 0.001, "{}", rrw);
+        assert!(characteristics.bias_instability_deg_h.unwrap() > 0.0);
+
+        let parameters = characteristics.noise_parameters().unwrap();
+        assert!((parameters.noise_density_dps_sqrt_hz - 0.03).abs() < 0.003, "{:?}", parameters);
+        assert_eq!(Some(parameters.bias_correlation_time_s), characteristics.bias_instability_tau_s);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn log_round_trip() {
+        let capture = Capture {
+            data_rate: DataRateBandwidth::ODR_400_CUT_OFF_110,
+            full_scale: FullScaleSelection::FSS_500_DPS,
+            rates_dps: vec![[0.125, -0.5, 3.0], [1.0, 0.0, -0.25]],
+        };
+        let log = capture.to_log();
+        assert!(log.starts_with("# dr_bw=0xb0 fs=0x10 odr_hz=400 cut_off_hz=110 full_scale_dps=500\n"));
+        assert_eq!(Capture::from_log(&log).unwrap(), capture);
+
+        // At 100 Hz, BW=01, 10 and 11 all mean a 25 Hz cut-off, and each is kept as it was set
+        for bits in [0x10, 0x20, 0x30] {
+            let data_rate = ctrlreg1::Value::from(bits).dr_bw;
+            let capture = Capture { data_rate, full_scale: ctrlreg4::Value::from(0x30).full_scale_select, rates_dps: vec![] };
+            assert_eq!(Capture::from_log(&capture.to_log()).unwrap(), capture);
+        }
+
+        assert!(matches!(Capture::from_log("1,2,3\n"), Err(CaptureError::MissingSettings())));
+        assert!(matches!(Capture::from_log("# dr_bw=176 fs=0x10\n"), Err(CaptureError::Format(1))));
+        let broken = "# dr_bw=0xb0 fs=0x10\n1,2,3\n1,2\n";
+        assert!(matches!(Capture::from_log(broken), Err(CaptureError::Format(3))));
+    }
+}
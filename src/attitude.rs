@@ -0,0 +1,285 @@
+use core::ops::Mul;
+
+use libm::{asin, atan2, cos, sin, sqrt};
+
+/// Order of the three elemental rotations making up a set of Euler (Tait-Bryan) angles.
+///
+/// `ZYX` means the orientation is obtained by rotating about Z, then about the new Y, then about
+/// the newest X (yaw, pitch, roll). Angles are always given in that same order.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+}
+
+impl EulerOrder {
+    fn axes(self) -> [usize; 3] {
+        match self {
+            EulerOrder::XYZ => [0, 1, 2],
+            EulerOrder::XZY => [0, 2, 1],
+            EulerOrder::YXZ => [1, 0, 2],
+            EulerOrder::YZX => [1, 2, 0],
+            EulerOrder::ZXY => [2, 0, 1],
+            EulerOrder::ZYX => [2, 1, 0],
+        }
+    }
+
+    // +1 when the axes follow X -> Y -> Z cyclically, -1 otherwise
+    fn parity(self) -> f64 {
+        match self {
+            EulerOrder::XYZ | EulerOrder::YZX | EulerOrder::ZXY => 1.0,
+            _ => -1.0,
+        }
+    }
+}
+
+/// Rotation stored as a quaternion `w + xi + yj + zk`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    /// Rotation of `angle` radians about `axis`, which does not need to be normalized. A zero axis
+    /// gives the identity.
+    pub fn from_axis_angle(axis: (f64, f64, f64), angle: f64) -> Self {
+        let norm = sqrt(axis.0 * axis.0 + axis.1 * axis.1 + axis.2 * axis.2);
+        if norm == 0.0 {
+            return Self::IDENTITY;
+        }
+        let s = sin(angle / 2.0) / norm;
+        Quaternion::new(cos(angle / 2.0), axis.0 * s, axis.1 * s, axis.2 * s)
+    }
+
+    /// Builds a rotation from Euler angles in radians, given in the order of `order`.
+    pub fn from_euler(angles: (f64, f64, f64), order: EulerOrder) -> Self {
+        let unit = |axis: usize| match axis {
+            0 => (1.0, 0.0, 0.0),
+            1 => (0.0, 1.0, 0.0),
+            _ => (0.0, 0.0, 1.0),
+        };
+        let [first, second, third] = order.axes();
+        Self::from_axis_angle(unit(first), angles.0)
+            * Self::from_axis_angle(unit(second), angles.1)
+            * Self::from_axis_angle(unit(third), angles.2)
+    }
+
+    pub fn norm(&self) -> f64 {
+        sqrt(self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z)
+    }
+
+    pub fn normalize(&self) -> Self {
+        let norm = self.norm();
+        Quaternion::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotates `vector` from the body frame into the reference frame.
+    pub fn rotate(&self, vector: (f64, f64, f64)) -> (f64, f64, f64) {
+        let rotated = *self * Quaternion::new(0.0, vector.0, vector.1, vector.2) * self.conjugate();
+        (rotated.x, rotated.y, rotated.z)
+    }
+
+    /// Rotation matrix taking body frame vectors into the reference frame, row by row.
+    pub fn to_rotation_matrix(&self) -> [[f64; 3]; 3] {
+        let Quaternion { w, x, y, z } = *self;
+        [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ]
+    }
+
+    /// Euler angles in radians, in the order of `order`. The middle angle is within ±π/2.
+    pub fn to_euler(&self, order: EulerOrder) -> (f64, f64, f64) {
+        let m = self.to_rotation_matrix();
+        let [i, j, k] = order.axes();
+        let s = order.parity();
+        // Rounding can push the sine slightly past ±1 at gimbal lock
+        let second = asin((s * m[i][k]).clamp(-1.0, 1.0));
+        let first = atan2(-s * m[j][k], m[k][k]);
+        let third = atan2(-s * m[i][j], m[i][i]);
+        (first, second, third)
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Tracks orientation by propagating a unit quaternion with angular rate samples (in degrees per
+/// second, in the sensor frame).
+///
+/// Each sample is applied as a rotation about its rate vector over the sample interval, so
+/// simultaneous rotation about several axes is handled correctly, unlike summing per-axis angles.
+#[derive(Debug, Clone, Default)]
+pub struct AttitudeEstimator {
+    orientation: Quaternion,
+    previous_timestamp: Option<f64>,
+}
+
+impl AttitudeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a rate held for `dt` seconds. Non-positive intervals are ignored.
+    pub fn update(&mut self, rate_dps: (f64, f64, f64), dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let rate = (rate_dps.0.to_radians(), rate_dps.1.to_radians(), rate_dps.2.to_radians());
+        let speed = sqrt(rate.0 * rate.0 + rate.1 * rate.1 + rate.2 * rate.2);
+        let step = Quaternion::from_axis_angle(rate, speed * dt);
+        self.orientation = (self.orientation * step).normalize();
+    }
+
+    /// Applies a rate sampled at `timestamp` seconds over the time since the previous timestamp.
+    /// The first call only records the timestamp, and timestamps that do not move forward are
+    /// ignored.
+    pub fn update_at(&mut self, rate_dps: (f64, f64, f64), timestamp: f64) {
+        match self.previous_timestamp {
+            Some(previous) if timestamp <= previous => return,
+            Some(previous) => self.update(rate_dps, timestamp - previous),
+            None => {}
+        }
+        self.previous_timestamp = Some(timestamp);
+    }
+
+    pub fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    pub fn rotation_matrix(&self) -> [[f64; 3]; 3] {
+        self.orientation.to_rotation_matrix()
+    }
+
+    pub fn euler_rad(&self, order: EulerOrder) -> (f64, f64, f64) {
+        self.orientation.to_euler(order)
+    }
+
+    pub fn euler_deg(&self, order: EulerOrder) -> (f64, f64, f64) {
+        let (a, b, c) = self.euler_rad(order);
+        (a.to_degrees(), b.to_degrees(), c.to_degrees())
+    }
+
+    /// Overrides the current orientation, e.g. with one derived from an absolute reference.
+    pub fn set_orientation(&mut self, orientation: Quaternion) {
+        self.orientation = orientation.normalize();
+    }
+
+    /// Goes back to the identity orientation and forgets the previous timestamp.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::FRAC_PI_2;
+
+    const ORDERS: [EulerOrder; 6] = [EulerOrder::XYZ, EulerOrder::XZY, EulerOrder::YXZ, EulerOrder::YZX, EulerOrder::ZXY, EulerOrder::ZYX];
+
+    fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        assert!((actual.1 - expected.1).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        assert!((actual.2 - expected.2).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn rotate_about_z() {
+        let q = Quaternion::from_axis_angle((0.0, 0.0, 2.0), FRAC_PI_2);
+        assert_close(q.rotate((1.0, 0.0, 0.0)), (0.0, 1.0, 0.0));
+        let m = q.to_rotation_matrix();
+        assert_close((m[0][0], m[1][0], m[2][0]), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let angles = (0.3, -0.7, 1.2);
+        for order in ORDERS {
+            let q = Quaternion::from_euler(angles, order);
+            assert_close(q.to_euler(order), angles);
+        }
+    }
+
+    #[test]
+    fn euler_matches_matrix() {
+        // Yaw 90° then pitch 90° (ZYX) turns the X axis straight down
+        let q = Quaternion::from_euler((FRAC_PI_2, FRAC_PI_2, 0.0), EulerOrder::ZYX);
+        assert_close(q.rotate((1.0, 0.0, 0.0)), (0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn integrates_rotation_about_two_axes() {
+        // 90° about X, then 60° about the new Y, in 100 steps each
+        let mut attitude = AttitudeEstimator::new();
+        for _ in 0..100 {
+            attitude.update((90.0, 0.0, 0.0), 0.01);
+        }
+        for _ in 0..100 {
+            attitude.update((0.0, 60.0, 0.0), 0.01);
+        }
+        let expected = Quaternion::from_euler((FRAC_PI_2, 60f64.to_radians(), 0.0), EulerOrder::XYZ);
+        let q = attitude.orientation();
+        assert!((q.w - expected.w).abs() < 1e-9 && (q.x - expected.x).abs() < 1e-9);
+        assert!((q.y - expected.y).abs() < 1e-9 && (q.z - expected.z).abs() < 1e-9);
+        assert_close(attitude.euler_deg(EulerOrder::XYZ), (90.0, 60.0, 0.0));
+    }
+
+    #[test]
+    fn simultaneous_rotation_stays_unit() {
+        let mut attitude = AttitudeEstimator::new();
+        for step in 0..10_000 {
+            attitude.update_at((30.0, -45.0, 60.0), step as f64 * 0.0025);
+        }
+        assert!((attitude.orientation().norm() - 1.0).abs() < 1e-12);
+        // Constant rate: a single rotation about the rate vector
+        let speed = sqrt(30.0 * 30.0 + 45.0 * 45.0 + 60.0 * 60.0).to_radians();
+        let expected = Quaternion::from_axis_angle((30.0, -45.0, 60.0), speed * 9999.0 * 0.0025);
+        assert!((attitude.orientation().w - expected.w).abs() < 1e-9);
+    }
+
+    #[test]
+    fn set_and_reset() {
+        let mut attitude = AttitudeEstimator::new();
+        attitude.set_orientation(Quaternion::new(2.0, 0.0, 0.0, 0.0));
+        assert_eq!(attitude.orientation(), Quaternion::IDENTITY);
+        attitude.update((0.0, 0.0, 90.0), 1.0);
+        assert_close(attitude.euler_deg(EulerOrder::ZYX), (90.0, 0.0, 0.0));
+        attitude.reset();
+        assert_eq!(attitude.orientation(), Quaternion::IDENTITY);
+    }
+}
@@ -0,0 +1,588 @@
+use libm::sqrt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use thiserror::Error;
+
+/// Version written by `Calibration::to_json`. Files with a newer version are rejected.
+pub const CALIBRATION_VERSION: u32 = 1;
+
+pub const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Identifies one sensor in a fleet: the bus number (e.g. N in `/dev/i2c-N`) and the I2C address
+/// or SPI chip select on it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceKey {
+    pub bus: u8,
+    pub address: u8,
+}
+
+/// Per-unit calibration, which can be extracted from a driver, stored, and applied again after a
+/// restart instead of calibrating on every boot.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Calibration {
+    pub version: u32,
+    /// Sensor this calibration was made for, if it should only be applied to that one
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub device: Option<DeviceKey>,
+    /// Zero-rate level in degrees per second, subtracted from every sample
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bias_dps: [f32; 3],
+    /// Applied to bias-free rates. The diagonal holds the scale factor of each axis, the other
+    /// terms the cross-axis misalignment
+    #[cfg_attr(feature = "serde", serde(default = "identity"))]
+    pub correction: [[f32; 3]; 3],
+    /// Temperature in °C at which OUT_TEMP reads zero, when a reference thermometer was used
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_offset: Option<i8>,
+    /// OUT_TEMP reading, in digits, at which `bias_dps` was measured. Raw digits do not depend on
+    /// `temperature_offset`, so setting a new one later does not move the model
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_reference: f32,
+    /// Change of the zero-rate level of each axis away from `temperature_reference`, in dps/°C
+    /// and dps/°C²
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature_coefficients: [[f32; 2]; 3],
+}
+
+#[cfg(feature = "serde")]
+fn identity() -> [[f32; 3]; 3] {
+    IDENTITY
+}
+
+impl Calibration {
+    /// Whether this calibration can be applied to the sensor at `bus` and `address`, which is the
+    /// case when it matches or was not made for a particular sensor.
+    pub fn matches(&self, bus: u8, address: u8) -> bool {
+        self.device.is_none_or(|device| device == DeviceKey { bus, address })
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            version: CALIBRATION_VERSION,
+            device: None,
+            bias_dps: [0.0; 3],
+            correction: IDENTITY,
+            temperature_offset: None,
+            temperature_reference: 0.0,
+            temperature_coefficients: [[0.0; 2]; 3],
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Error)]
+pub enum CalibrationError {
+    #[error("Failed to access calibration file")]
+    Io(std::io::Error),
+    #[error("Malformed calibration data")]
+    Format(serde_json::Error),
+    #[error("Unsupported calibration version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[cfg(feature = "serde")]
+impl Calibration {
+    pub fn to_json(&self) -> Result<String, CalibrationError> {
+        serde_json::to_string_pretty(self).map_err(CalibrationError::Format)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, CalibrationError> {
+        let calibration: Calibration = serde_json::from_str(json).map_err(CalibrationError::Format)?;
+        if calibration.version > CALIBRATION_VERSION {
+            return Err(CalibrationError::UnsupportedVersion(calibration.version));
+        }
+        Ok(calibration)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), CalibrationError> {
+        std::fs::write(path, self.to_json()?).map_err(CalibrationError::Io)
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, CalibrationError> {
+        Self::from_json(&std::fs::read_to_string(path).map_err(CalibrationError::Io)?)
+    }
+}
+
+/// One recorded rotation and what it should have read. Both vectors use the same unit: mean rates
+/// in dps for a turntable spinning at a known speed, or integrated angles in degrees for a
+/// rotation through a known angle (e.g. exactly 360° about one axis).
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ReferenceRotation {
+    pub measured: [f32; 3],
+    pub reference: [f32; 3],
+}
+
+/// Result of `fit_correction` or `fit_correction_with_bias`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CorrectionFit {
+    pub correction: [[f32; 3]; 3],
+    pub bias_dps: [f32; 3],
+    /// Root mean square of what the corrected measurements still miss the references by
+    pub residual_rms: f32,
+}
+
+impl CorrectionFit {
+    /// Stores the fitted model in `calibration`. The bias is only replaced by fits that include
+    /// one.
+    pub fn apply_to(&self, calibration: &mut Calibration, with_bias: bool) {
+        calibration.correction = self.correction;
+        if with_bias {
+            calibration.bias_dps = self.bias_dps;
+        }
+    }
+}
+
+// Gauss-Jordan elimination with partial pivoting
+fn invert<const N: usize>(mut m: [[f64; N]; N]) -> Option<[[f64; N]; N]> {
+    let mut inverse = [[0.0; N]; N];
+    for (i, row) in inverse.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for column in 0..N {
+        let pivot = (column..N).max_by(|&a, &b| m[a][column].abs().total_cmp(&m[b][column].abs()))?;
+        if m[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let scale = m[column][column];
+        for k in 0..N {
+            m[column][k] /= scale;
+            inverse[column][k] /= scale;
+        }
+        for row in 0..N {
+            if row != column {
+                let factor = m[row][column];
+                for k in 0..N {
+                    m[row][k] -= factor * m[column][k];
+                    inverse[row][k] -= factor * inverse[column][k];
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
+
+// Least squares A minimizing |A a_i - r_i| over all observations, where a_i is built from the
+// measurement by `augment`: A = (Σ r aᵀ)(Σ a aᵀ)⁻¹
+fn fit_linear<const N: usize>(observations: &[ReferenceRotation], augment: impl Fn(&[f32; 3]) -> [f64; N]) -> Option<([[f64; N]; 3], f32)> {
+    let mut aa = [[0.0; N]; N];
+    let mut ra = [[0.0; N]; 3];
+    for observation in observations {
+        let a = augment(&observation.measured);
+        for i in 0..N {
+            for j in 0..N {
+                aa[i][j] += a[i] * a[j];
+            }
+        }
+        for (row, reference) in ra.iter_mut().zip(observation.reference) {
+            for j in 0..N {
+                row[j] += reference as f64 * a[j];
+            }
+        }
+    }
+    let aa_inverse = invert(aa)?;
+    let fit = ra.map(|row| core::array::from_fn(|j| (0..N).map(|k| row[k] * aa_inverse[k][j]).sum::<f64>()));
+
+    let mut squared_error = 0.0;
+    for observation in observations {
+        let a = augment(&observation.measured);
+        for (i, row) in fit.iter().enumerate() {
+            let error = (0..N).map(|k| row[k] * a[k]).sum::<f64>() - observation.reference[i] as f64;
+            squared_error += error * error;
+        }
+    }
+    let residual_rms = sqrt(squared_error / (3 * observations.len()) as f64) as f32;
+    Some((fit, residual_rms))
+}
+
+fn to_f32(matrix: &[[f64; 3]; 3]) -> [[f32; 3]; 3] {
+    matrix.map(|row| row.map(|value| value as f32))
+}
+
+/// Fits the scale and misalignment matrix taking `measured` to `reference`, assuming the bias was
+/// already removed (e.g. by `calibrate_drift_at_rest`). Needs rotations about at least three
+/// independent axes, otherwise returns `None`.
+///
+/// Record the measurements with the identity correction, since the result replaces it.
+pub fn fit_correction(observations: &[ReferenceRotation]) -> Option<CorrectionFit> {
+    let (fit, residual_rms) = fit_linear(observations, |m| m.map(|value| value as f64))?;
+    Some(CorrectionFit { correction: to_f32(&fit), bias_dps: [0.0; 3], residual_rms })
+}
+
+/// Fits the scale and misalignment matrix together with the bias, from mean rates in dps. Needs
+/// at least four rates that do not lie on a plane, e.g. the device at rest and spinning about
+/// each axis. Otherwise returns `None`.
+///
+/// Record the measurements with `Calibration::default()` applied, since the result replaces it.
+pub fn fit_correction_with_bias(observations: &[ReferenceRotation]) -> Option<CorrectionFit> {
+    let (fit, residual_rms) = fit_linear(observations, |m| [m[0] as f64, m[1] as f64, m[2] as f64, 1.0])?;
+    // reference = M (measured - bias) = M measured + c, so bias = -M⁻¹ c
+    let correction: [[f64; 3]; 3] = fit.map(|row| [row[0], row[1], row[2]]);
+    let inverse = invert(correction)?;
+    let bias_dps = inverse.map(|row| -(row[0] * fit[0][3] + row[1] * fit[1][3] + row[2] * fit[2][3]) as f32);
+    Some(CorrectionFit { correction: to_f32(&correction), bias_dps, residual_rms })
+}
+
+/// Zero-rate level measured at one temperature, e.g. by `L3G4200D::measure_temperature_bias`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TemperatureSample {
+    /// OUT_TEMP reading in digits, as returned by `read_raw_temperature_delta`, which goes down by
+    /// one per °C
+    pub temperature: f32,
+    pub bias_dps: [f32; 3],
+}
+
+/// Shape of the bias against temperature curve fitted by `fit_temperature_model`. Only
+/// polynomials of degree 1 and 2 are supported.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TemperatureModel {
+    Linear,
+    Quadratic,
+}
+
+impl TemperatureModel {
+    /// Model for a polynomial of `degree`, or `None` for any degree other than 1 and 2.
+    pub fn from_degree(degree: u32) -> Option<Self> {
+        match degree {
+            1 => Some(TemperatureModel::Linear),
+            2 => Some(TemperatureModel::Quadratic),
+            _ => None,
+        }
+    }
+
+    pub fn degree(&self) -> u32 {
+        match self {
+            TemperatureModel::Linear => 1,
+            TemperatureModel::Quadratic => 2,
+        }
+    }
+}
+
+/// Result of `fit_temperature_model`: the bias at the reference temperature, and how it changes
+/// away from it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TemperatureFit {
+    /// In OUT_TEMP digits
+    pub reference: f32,
+    pub bias_dps: [f32; 3],
+    /// Per axis, in dps/°C and dps/°C²
+    pub coefficients: [[f32; 2]; 3],
+}
+
+impl TemperatureFit {
+    /// Stores the fitted model, bias included, in `calibration`.
+    pub fn apply_to(&self, calibration: &mut Calibration) {
+        calibration.bias_dps = self.bias_dps;
+        calibration.temperature_reference = self.reference;
+        calibration.temperature_coefficients = self.coefficients;
+    }
+}
+
+/// Change of the zero-rate level at `temperature` relative to `reference`, both in OUT_TEMP
+/// digits.
+pub(crate) fn temperature_drift(coefficients: &[[f32; 2]; 3], reference: f32, temperature: f32) -> [f32; 3] {
+    // OUT_TEMP goes down by one digit per °C
+    let delta = reference - temperature;
+    coefficients.map(|[linear, quadratic]| linear * delta + quadratic * delta * delta)
+}
+
+// Least squares coefficients of 1, ΔT, ΔT², ... per axis, with ΔT in °C
+fn fit_polynomial<const N: usize>(samples: &[TemperatureSample], reference: f64) -> Option<[[f64; N]; 3]> {
+    let mut normal = [[0.0; N]; N];
+    let mut moments = [[0.0; N]; 3];
+    for sample in samples {
+        let delta = reference - sample.temperature as f64;
+        let mut powers = [1.0; N];
+        for i in 1..N {
+            powers[i] = powers[i - 1] * delta;
+        }
+        for i in 0..N {
+            for j in 0..N {
+                normal[i][j] += powers[i] * powers[j];
+            }
+        }
+        for (axis, bias) in moments.iter_mut().zip(sample.bias_dps) {
+            for i in 0..N {
+                axis[i] += bias as f64 * powers[i];
+            }
+        }
+    }
+    let inverse = invert(normal)?;
+    Some(moments.map(|axis| core::array::from_fn(|i| (0..N).map(|k| inverse[i][k] * axis[k]).sum::<f64>())))
+}
+
+/// Fits the zero-rate level of each axis against temperature by least squares, with the mean
+/// temperature as reference. Only linear and quadratic polynomials are fitted, not lookup tables
+/// or higher degrees. Needs samples at two (linear) or three (quadratic) different temperatures,
+/// otherwise returns `None`.
+pub fn fit_temperature_model(samples: &[TemperatureSample], model: TemperatureModel) -> Option<TemperatureFit> {
+    if samples.is_empty() {
+        return None;
+    }
+    let reference = samples.iter().map(|sample| sample.temperature as f64).sum::<f64>() / samples.len() as f64;
+    let solution = match model {
+        TemperatureModel::Linear => fit_polynomial::<2>(samples, reference)?.map(|[bias, linear]| [bias, linear, 0.0]),
+        TemperatureModel::Quadratic => fit_polynomial::<3>(samples, reference)?,
+    };
+    Some(TemperatureFit {
+        reference: reference as f32,
+        bias_dps: solution.map(|axis| axis[0] as f32),
+        coefficients: solution.map(|axis| [axis[1] as f32, axis[2] as f32]),
+    })
+}
+
+/// Settings for `L3G4200D::calibrate_drift_at_rest`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DriftCalibrationOptions {
+    /// Number of fresh samples to average. At least 2 are needed to check for motion
+    pub samples: u32,
+    /// Largest standard deviation, on any axis, still considered as being at rest
+    pub max_std_dev_dps: f32,
+    /// How long to wait for each sample before giving up
+    pub timeout_us: u32,
+}
+
+impl Default for DriftCalibrationOptions {
+    fn default() -> Self {
+        DriftCalibrationOptions {
+            samples: 200,
+            max_std_dev_dps: 1.0,
+            timeout_us: 100_000,
+        }
+    }
+}
+
+/// Statistics of one axis over a calibration window, in digits (raw output counts).
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct AxisStatistics {
+    pub mean: f32,
+    /// Sample standard deviation
+    pub std_dev: f32,
+    pub min: i16,
+    pub max: i16,
+    pub samples: u32,
+}
+
+/// Result of a drift calibration, per axis.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct DriftStatistics {
+    pub x: AxisStatistics,
+    pub y: AxisStatistics,
+    pub z: AxisStatistics,
+}
+
+impl DriftStatistics {
+    pub fn mean(&self) -> [f32; 3] {
+        [self.x.mean, self.y.mean, self.z.mean]
+    }
+
+    pub fn max_std_dev(&self) -> f32 {
+        self.x.std_dev.max(self.y.std_dev).max(self.z.std_dev)
+    }
+}
+
+/// Running mean and variance (Welford), so that long windows keep their precision.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AxisAccumulator {
+    count: u32,
+    mean: f64,
+    m2: f64,
+    min: i16,
+    max: i16,
+}
+
+impl AxisAccumulator {
+    pub(crate) fn new() -> Self {
+        AxisAccumulator { count: 0, mean: 0.0, m2: 0.0, min: i16::MAX, max: i16::MIN }
+    }
+
+    pub(crate) fn push(&mut self, value: i16) {
+        self.count += 1;
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub(crate) fn statistics(&self) -> AxisStatistics {
+        if self.count == 0 {
+            return AxisStatistics::default();
+        }
+        let variance = if self.count > 1 { self.m2 / (self.count - 1) as f64 } else { 0.0 };
+        AxisStatistics {
+            mean: self.mean as f32,
+            std_dev: sqrt(variance) as f32,
+            min: self.min,
+            max: self.max,
+            samples: self.count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration_matches_device() {
+        let mut calibration = Calibration::default();
+        assert!(calibration.matches(1, 0x69));
+        calibration.device = Some(DeviceKey { bus: 1, address: 0x68 });
+        assert!(calibration.matches(1, 0x68));
+        assert!(!calibration.matches(1, 0x69));
+        assert!(!calibration.matches(2, 0x68));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn calibration_json_round_trip() {
+        let calibration = Calibration {
+            device: Some(DeviceKey { bus: 1, address: 0x69 }),
+            bias_dps: [0.25, -1.5, 0.0],
+            correction: [[1.02, 0.01, 0.0], [0.0, 0.99, 0.0], [0.0, -0.02, 1.0]],
+            temperature_offset: Some(27),
+            temperature_reference: 25.0,
+            temperature_coefficients: [[0.01, 0.0], [0.0, 0.0], [-0.02, 0.001]],
+            ..Default::default()
+        };
+        assert_eq!(Calibration::from_json(&calibration.to_json().unwrap()).unwrap(), calibration);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn calibration_json_versions() {
+        let calibration = Calibration::from_json(r#"{ "version": 1, "bias_dps": [1.0, 2.0, 3.0] }"#).unwrap();
+        assert_eq!(calibration, Calibration { bias_dps: [1.0, 2.0, 3.0], ..Default::default() });
+
+        let result = Calibration::from_json(r#"{ "version": 2 }"#);
+        assert!(matches!(result, Err(CalibrationError::UnsupportedVersion(2))));
+        assert!(matches!(Calibration::from_json(r#"{ "bias_dps": [] }"#), Err(CalibrationError::Format(_))));
+    }
+
+    const SCALE_MISALIGNMENT: [[f64; 3]; 3] = [[1.03, 0.02, -0.01], [-0.015, 0.97, 0.005], [0.01, 0.03, 1.01]];
+
+    // What the sensor reads for `reference`, given the true model and bias
+    fn measure(reference: [f32; 3], bias: [f64; 3]) -> ReferenceRotation {
+        let inverse = invert(SCALE_MISALIGNMENT).unwrap();
+        let measured = [0, 1, 2].map(|i| ((0..3).map(|k| inverse[i][k] * reference[k] as f64).sum::<f64>() + bias[i]) as f32);
+        ReferenceRotation { measured, reference }
+    }
+
+    fn assert_matrix_close(actual: &[[f32; 3]; 3], expected: &[[f64; 3]; 3]) {
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((actual[i][j] as f64 - expected[i][j]).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn invert_matrix() {
+        let inverse = invert([[2.0, 0.0, 1.0], [1.0, 1.0, 0.0], [0.0, 3.0, 1.0]]).unwrap();
+        assert_matrix_close(&to_f32(&inverse), &[[0.2, 0.6, -0.2], [-0.2, 0.4, 0.2], [0.6, -1.2, 0.4]]);
+        assert!(invert([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]]).is_none());
+    }
+
+    #[test]
+    fn fit_correction_from_full_turns() {
+        let observations = [[360.0, 0.0, 0.0], [0.0, -360.0, 0.0], [0.0, 0.0, 360.0]].map(|reference| measure(reference, [0.0; 3]));
+        let fit = fit_correction(&observations).unwrap();
+        assert_matrix_close(&fit.correction, &SCALE_MISALIGNMENT);
+        assert!(fit.residual_rms < 1e-3);
+
+        // Rotations about a single axis say nothing about the others
+        assert!(fit_correction(&observations[..1]).is_none());
+    }
+
+    #[test]
+    fn fit_correction_with_bias_from_turntable() {
+        let bias = [0.8, -1.2, 0.3];
+        let observations = [[0.0, 0.0, 0.0], [100.0, 0.0, 0.0], [0.0, 100.0, 0.0], [0.0, 0.0, -100.0], [50.0, 50.0, 50.0]]
+            .map(|reference| measure(reference, bias));
+        let fit = fit_correction_with_bias(&observations).unwrap();
+        assert_matrix_close(&fit.correction, &SCALE_MISALIGNMENT);
+        for (fitted, expected) in fit.bias_dps.iter().zip(bias) {
+            assert!((*fitted as f64 - expected).abs() < 1e-4, "{:?}", fit.bias_dps);
+        }
+
+        let mut calibration = Calibration::default();
+        fit.apply_to(&mut calibration, true);
+        assert_eq!((calibration.correction, calibration.bias_dps), (fit.correction, fit.bias_dps));
+    }
+
+    fn warm_up(bias: impl Fn(f32) -> [f32; 3]) -> [TemperatureSample; 6] {
+        // OUT_TEMP reads 0 at 0 °C
+        [20.0, 24.0, 29.0, 33.0, 38.0, 45.0].map(|temperature| TemperatureSample { temperature: -temperature, bias_dps: bias(temperature) })
+    }
+
+    #[test]
+    fn fit_linear_temperature_model() {
+        let samples = warm_up(|t| [0.5 + 0.02 * (t - 25.0), -1.0, 0.3 - 0.05 * (t - 25.0)]);
+        let fit = fit_temperature_model(&samples, TemperatureModel::Linear).unwrap();
+        assert!((fit.reference + 31.5).abs() < 1e-5);
+        let expected = [0.5 + 0.02 * 6.5, -1.0, 0.3 - 0.05 * 6.5];
+        for (fitted, expected) in fit.bias_dps.iter().zip(expected) {
+            assert!((fitted - expected).abs() < 1e-5, "{:?}", fit);
+        }
+        assert!((fit.coefficients[0][0] - 0.02).abs() < 1e-6 && fit.coefficients[0][1] == 0.0);
+        assert!(fit.coefficients[1][0].abs() < 1e-6);
+        assert!((fit.coefficients[2][0] + 0.05).abs() < 1e-6);
+
+        assert!(fit_temperature_model(&samples[..1], TemperatureModel::Linear).is_none());
+        assert!(fit_temperature_model(&[], TemperatureModel::Linear).is_none());
+    }
+
+    #[test]
+    fn temperature_model_degrees() {
+        assert_eq!(TemperatureModel::from_degree(1), Some(TemperatureModel::Linear));
+        assert_eq!(TemperatureModel::from_degree(2).map(|model| model.degree()), Some(2));
+        assert_eq!(TemperatureModel::from_degree(0), None);
+        assert_eq!(TemperatureModel::from_degree(3), None);
+    }
+
+    #[test]
+    fn fit_quadratic_temperature_model() {
+        let bias = |t: f32| [0.001 * (t - 30.0) * (t - 30.0), 0.1 * t, 0.0];
+        let fit = fit_temperature_model(&warm_up(bias), TemperatureModel::Quadratic).unwrap();
+        for temperature in [15.0, 30.0, 50.0] {
+            let drift = temperature_drift(&fit.coefficients, fit.reference, -temperature);
+            let expected = bias(temperature);
+            for axis in 0..3 {
+                assert!((fit.bias_dps[axis] + drift[axis] - expected[axis]).abs() < 1e-4, "{:?}", fit);
+            }
+        }
+
+        let mut calibration = Calibration::default();
+        fit.apply_to(&mut calibration);
+        assert_eq!(calibration.temperature_reference, fit.reference);
+    }
+
+    #[test]
+    fn accumulator_statistics() {
+        let mut accumulator = AxisAccumulator::new();
+        for value in [2, 4, 4, 4, 5, 5, 7, 9] {
+            accumulator.push(value);
+        }
+        let statistics = accumulator.statistics();
+        assert_eq!(statistics.mean, 5.0);
+        assert!((statistics.std_dev - 2.13809).abs() < 1e-5);
+        assert_eq!((statistics.min, statistics.max, statistics.samples), (2, 9, 8));
+    }
+
+    #[test]
+    fn accumulator_keeps_sub_digit_mean() {
+        let mut accumulator = AxisAccumulator::new();
+        for value in [-3, -4, -4, -4] {
+            accumulator.push(value);
+        }
+        assert_eq!(accumulator.statistics().mean, -3.75);
+        assert_eq!(AxisAccumulator::new().statistics(), AxisStatistics::default());
+    }
+}
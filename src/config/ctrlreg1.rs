@@ -1,6 +1,6 @@
 use bitmask::bitmask;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct DataRateBandwidth(u8);
 
 impl DataRateBandwidth {
@@ -18,6 +18,45 @@ impl DataRateBandwidth {
     pub const ODR_800_CUT_OFF_35:   DataRateBandwidth = DataRateBandwidth {0: 0xD0};
     pub const ODR_800_CUT_OFF_50:   DataRateBandwidth = DataRateBandwidth {0: 0xE0};
     pub const ODR_800_CUT_OFF_110:  DataRateBandwidth = DataRateBandwidth {0: 0xF0};
+
+    /// Output data rate in Hz, selected by the DR bits.
+    pub fn output_data_rate_hz(&self) -> f32 {
+        match self.0 & 0xC0 {
+            0x00 => 100.0,
+            0x40 => 200.0,
+            0x80 => 400.0,
+            _ => 800.0,
+        }
+    }
+
+    /// Cut-off frequency of the low-pass filter in Hz, selected by the DR and BW bits together.
+    pub fn cut_off_hz(&self) -> f32 {
+        match self.0 {
+            0x00 => 12.5,
+            0x10 | 0x20 | 0x30 => 25.0,
+            0x40 => 12.5,
+            0x50 => 25.0,
+            0x60 => 50.0,
+            0x70 => 70.0,
+            0x80 => 20.0,
+            0x90 => 25.0,
+            0xA0 => 50.0,
+            0xB0 => 110.0,
+            0xC0 => 30.0,
+            0xD0 => 35.0,
+            0xE0 => 50.0,
+            _ => 110.0,
+        }
+    }
+
+    /// Setting with the given output data rate and cut-off, both in Hz. `None` when no setting or
+    /// several match, as for 25 Hz at 100 Hz where BW = 01, 10 and 11 are all the same.
+    pub fn from_hz(output_data_rate_hz: f32, cut_off_hz: f32) -> Option<Self> {
+        let mut matching = (0..16).map(|bits| DataRateBandwidth(bits << 4))
+            .filter(|setting| setting.output_data_rate_hz() == output_data_rate_hz && setting.cut_off_hz() == cut_off_hz);
+        let setting = matching.next()?;
+        matching.next().is_none().then_some(setting)
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -110,4 +149,22 @@ mod tests {
         //assert_eq!(config.to_value(), 0x6F);
         println!("{:?}", config);
     }
+
+    #[test]
+    fn output_data_rate() {
+        assert_eq!(DataRateBandwidth::ODR_100_CUT_OFF_25.output_data_rate_hz(), 100.0);
+        assert_eq!(DataRateBandwidth::ODR_200_CUT_OFF_50.output_data_rate_hz(), 200.0);
+        assert_eq!(DataRateBandwidth::ODR_400_CUT_OFF_110.output_data_rate_hz(), 400.0);
+        assert_eq!(DataRateBandwidth::ODR_800_CUT_OFF_30.output_data_rate_hz(), 800.0);
+    }
+
+    #[test]
+    fn cut_off_round_trip() {
+        assert_eq!(DataRateBandwidth::ODR_200_CUT_OFF_70.cut_off_hz(), 70.0);
+        assert_eq!(DataRateBandwidth::ODR_400_CUT_OFF_20.cut_off_hz(), 20.0);
+        assert_eq!(DataRateBandwidth::from_hz(800.0, 35.0), Some(DataRateBandwidth::ODR_800_CUT_OFF_35));
+        assert_eq!(DataRateBandwidth::from_hz(100.0, 12.5), Some(DataRateBandwidth::ODR_100_CUT_OFF_12_5));
+        assert_eq!(DataRateBandwidth::from_hz(100.0, 25.0), None);
+        assert_eq!(DataRateBandwidth::from_hz(200.0, 110.0), None);
+    }
 }
\ No newline at end of file
@@ -39,6 +39,7 @@ impl Value {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Value {
     fn default() -> Self { 
         Value {
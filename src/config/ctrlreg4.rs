@@ -8,7 +8,7 @@ impl BlockDataUpdate {
     pub const WAIT_FOR_READING:  BlockDataUpdate = BlockDataUpdate {0: true};
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct BigLittleEndian(bool);
 
 impl BigLittleEndian {
@@ -16,16 +16,53 @@ impl BigLittleEndian {
     pub const BIG_ENDIAN:    BigLittleEndian = BigLittleEndian {0: true};
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct FullScaleSelection(u8);
 
 impl FullScaleSelection {
     pub const FSS_250_DPS:  FullScaleSelection = FullScaleSelection {0: 0x00};
     pub const FSS_500_DPS:  FullScaleSelection = FullScaleSelection {0: 0x10};
     pub const FSS_2000_DPS: FullScaleSelection = FullScaleSelection {0: 0x20};
+
+    /// Sensitivity in millidegrees per second per digit, from the datasheet.
+    pub fn sensitivity_mdps(&self) -> f32 {
+        match self.0 {
+            0x00 => 8.75,
+            0x10 => 17.5,
+            _ => 70.0,
+        }
+    }
+
+    /// Measurement range, in degrees per second.
+    pub fn full_scale_dps(&self) -> f32 {
+        match self.0 {
+            0x00 => 250.0,
+            0x10 => 500.0,
+            _ => 2000.0,
+        }
+    }
+
+    /// Setting with the given measurement range in degrees per second, if there is one.
+    pub fn from_dps(full_scale_dps: f32) -> Option<Self> {
+        [Self::FSS_250_DPS, Self::FSS_500_DPS, Self::FSS_2000_DPS].into_iter().find(|setting| setting.full_scale_dps() == full_scale_dps)
+    }
+
+    /// Typical output change in self-test mode, in degrees per second, from the datasheet.
+    pub fn self_test_output_change_dps(&self) -> f32 {
+        match self.0 {
+            0x00 => 130.0,
+            0x10 => 200.0,
+            _ => 530.0,
+        }
+    }
+
+    /// Converts a raw output reading to degrees per second.
+    pub fn to_dps(&self, raw: i16) -> f32 {
+        raw as f32 * self.sensitivity_mdps() / 1000.0
+    }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct SelfTestEnabled(u8);
 
 impl SelfTestEnabled {
@@ -34,7 +71,7 @@ impl SelfTestEnabled {
     pub const SELF_TEST_1: SelfTestEnabled = SelfTestEnabled {0: 0x06};
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct SerialInterfaceMode(bool);
 
 impl SerialInterfaceMode {
@@ -113,4 +150,22 @@ mod tests {
         let config: Value = Value::from(0x20);
         assert_eq!(config.to_value(), 0x20);
     }
+
+    #[test]
+    fn full_scale_sensitivity() {
+        assert_eq!(FullScaleSelection::FSS_250_DPS.sensitivity_mdps(), 8.75);
+        assert_eq!(FullScaleSelection::FSS_500_DPS.sensitivity_mdps(), 17.5);
+        assert_eq!(FullScaleSelection::FSS_2000_DPS.sensitivity_mdps(), 70.0);
+        assert_eq!(FullScaleSelection::FSS_250_DPS.to_dps(-400), -3.5);
+        assert_eq!(FullScaleSelection::FSS_2000_DPS.to_dps(i16::MAX), 2293.69);
+        assert_eq!(FullScaleSelection::from_dps(500.0), Some(FullScaleSelection::FSS_500_DPS));
+        assert_eq!(FullScaleSelection::from_dps(1000.0), None);
+    }
+
+    #[test]
+    fn parse_self_test_from_u8() {
+        assert_eq!(Value::from(0x22).self_test_enabled, SelfTestEnabled::SELF_TEST_0);
+        assert_eq!(Value::from(0x06).self_test_enabled, SelfTestEnabled::SELF_TEST_1);
+        assert_eq!(FullScaleSelection::FSS_500_DPS.self_test_output_change_dps(), 200.0);
+    }
 }
\ No newline at end of file
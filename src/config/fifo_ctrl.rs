@@ -0,0 +1,76 @@
+use bitmask::bitmask;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct FifoMode(u8);
+
+impl FifoMode {
+    pub const BYPASS:           FifoMode = FifoMode {0: 0x00};
+    pub const FIFO:             FifoMode = FifoMode {0: 0x20};
+    pub const STREAM:           FifoMode = FifoMode {0: 0x40};
+    pub const STREAM_TO_FIFO:   FifoMode = FifoMode {0: 0x60};
+    pub const BYPASS_TO_STREAM: FifoMode = FifoMode {0: 0x80};
+}
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        Fm = 0xE0,
+        Wtm = 0x1F,
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Value {
+    pub fifo_mode: FifoMode,
+    /// Watermark level in samples (0 to 31)
+    pub watermark: u8,
+}
+
+impl Value {
+    pub fn to_value(self) -> u8 {
+        self.fifo_mode.0 | (self.watermark & *Flags::Wtm)
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value {
+            fifo_mode: FifoMode::BYPASS,
+            watermark: 0,
+        }
+    }
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Value {
+            fifo_mode: FifoMode(value & *Flags::Fm),
+            watermark: value & *Flags::Wtm,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_fifo_ctrl() {
+        let config: Value = Value::default();
+        assert_eq!(config.to_value(), 0x00);
+    }
+
+    #[test]
+    fn parse_fifo_ctrl_1() {
+        let config: Value = Value {
+            fifo_mode: FifoMode::STREAM,
+            watermark: 20,
+        };
+        assert_eq!(config.to_value(), 0x54);
+    }
+
+    #[test]
+    fn parse_fifo_ctrl_from_u8_1() {
+        let config: Value = Value::from(0x9F);
+        assert_eq!(config, Value { fifo_mode: FifoMode::BYPASS_TO_STREAM, watermark: 31 });
+    }
+}
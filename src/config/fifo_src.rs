@@ -0,0 +1,75 @@
+use bitmask::bitmask;
+
+pub const FIFO_DEPTH: usize = 32;
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        Wtm = 0x80,
+        Ovrn = 0x40,
+        Empty = 0x20,
+        Fss = 0x1F,
+    }
+}
+
+/// Decoded FIFO_SRC, which is read-only.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Value {
+    /// Stored samples reached the FIFO_CTRL watermark
+    pub watermark: bool,
+    /// FIFO is full; in Stream mode the oldest sample is being overwritten
+    pub overrun: bool,
+    pub empty: bool,
+    pub stored_data_level: u8,
+}
+
+impl Value {
+    /// Number of samples waiting to be read. A full FIFO only fits in FSS4-0 through OVRN.
+    pub fn len(&self) -> usize {
+        if self.overrun {
+            FIFO_DEPTH
+        } else if self.empty {
+            0
+        } else {
+            self.stored_data_level as usize
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Value {
+            watermark: *Flags::Wtm & value != 0,
+            overrun: *Flags::Ovrn & value != 0,
+            empty: *Flags::Empty & value != 0,
+            stored_data_level: value & *Flags::Fss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fifo_src_empty() {
+        let status: Value = Value::from(0x20);
+        assert!(status.is_empty());
+    }
+
+    #[test]
+    fn parse_fifo_src_partial() {
+        let status: Value = Value::from(0x8C);
+        assert!(status.watermark);
+        assert_eq!(status.len(), 12);
+    }
+
+    #[test]
+    fn parse_fifo_src_full() {
+        let status: Value = Value::from(0xDF);
+        assert_eq!(status.len(), FIFO_DEPTH);
+    }
+}
@@ -0,0 +1,108 @@
+use bitmask::bitmask;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct EventCombination(bool);
+
+impl EventCombination {
+    pub const OR:  EventCombination = EventCombination {0: false};
+    pub const AND: EventCombination = EventCombination {0: true};
+}
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        AndOr = 0x80,
+        Lir = 0x40,
+        Zhie = 0x20,
+        Zlie = 0x10,
+        Yhie = 0x08,
+        Ylie = 0x04,
+        Xhie = 0x02,
+        Xlie = 0x01,
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Value {
+    pub combination: EventCombination,
+    /// Keep INT1_SRC latched until it is read
+    pub latch_interrupt: bool,
+    pub z_high_enable: bool,
+    pub z_low_enable: bool,
+    pub y_high_enable: bool,
+    pub y_low_enable: bool,
+    pub x_high_enable: bool,
+    pub x_low_enable: bool,
+}
+
+impl Value {
+    pub fn to_value(self) -> u8 {
+        let combination: u8 = if self.combination.0   { *Flags::AndOr } else { 0 };
+        let lir:         u8 = if self.latch_interrupt { *Flags::Lir }   else { 0 };
+        let zhie:        u8 = if self.z_high_enable   { *Flags::Zhie }  else { 0 };
+        let zlie:        u8 = if self.z_low_enable    { *Flags::Zlie }  else { 0 };
+        let yhie:        u8 = if self.y_high_enable   { *Flags::Yhie }  else { 0 };
+        let ylie:        u8 = if self.y_low_enable    { *Flags::Ylie }  else { 0 };
+        let xhie:        u8 = if self.x_high_enable   { *Flags::Xhie }  else { 0 };
+        let xlie:        u8 = if self.x_low_enable    { *Flags::Xlie }  else { 0 };
+        combination | lir | zhie | zlie | yhie | ylie | xhie | xlie
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value {
+            combination: EventCombination::OR,
+            latch_interrupt: false,
+            z_high_enable: false,
+            z_low_enable: false,
+            y_high_enable: false,
+            y_low_enable: false,
+            x_high_enable: false,
+            x_low_enable: false,
+        }
+    }
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Value {
+            combination: EventCombination(*Flags::AndOr & value != 0),
+            latch_interrupt: *Flags::Lir & value != 0,
+            z_high_enable: *Flags::Zhie & value != 0,
+            z_low_enable: *Flags::Zlie & value != 0,
+            y_high_enable: *Flags::Yhie & value != 0,
+            y_low_enable: *Flags::Ylie & value != 0,
+            x_high_enable: *Flags::Xhie & value != 0,
+            x_low_enable: *Flags::Xlie & value != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_int1_cfg() {
+        let config: Value = Value::default();
+        assert_eq!(config.to_value(), 0x00);
+    }
+
+    #[test]
+    fn parse_int1_cfg_1() {
+        let config: Value = Value {
+            latch_interrupt: true,
+            z_high_enable: true,
+            y_high_enable: true,
+            x_high_enable: true,
+            ..Default::default()
+        };
+        assert_eq!(config.to_value(), 0x6A);
+    }
+
+    #[test]
+    fn parse_int1_cfg_from_u8_1() {
+        let config: Value = Value::from(0x95);
+        assert_eq!(config.to_value(), 0x95);
+    }
+}
@@ -0,0 +1,76 @@
+use bitmask::bitmask;
+
+use crate::config::ctrlreg1::DataRateBandwidth;
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        Wait = 0x80,
+        D = 0x7F,
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Value {
+    /// Also wait `duration` before the interrupt is released, instead of falling immediately
+    pub wait: bool,
+    /// Minimum time an event has to last to be recognized, in 1/ODR steps
+    pub duration: u8,
+}
+
+impl Value {
+    /// Converts a duration in seconds using the output data rate of `dr_bw`, which has to match
+    /// the rate CTRL_REG1 is set to. Durations longer than 127 samples are clamped.
+    pub fn from_seconds(wait: bool, seconds: f32, dr_bw: &DataRateBandwidth) -> Self {
+        let samples = seconds * dr_bw.output_data_rate_hz();
+        Value {
+            wait,
+            duration: ((samples + 0.5) as u8).min(*Flags::D),
+        }
+    }
+
+    pub fn to_seconds(&self, dr_bw: &DataRateBandwidth) -> f32 {
+        self.duration as f32 / dr_bw.output_data_rate_hz()
+    }
+
+    pub fn to_value(self) -> u8 {
+        let wait: u8 = if self.wait { *Flags::Wait } else { 0 };
+        wait | (self.duration & *Flags::D)
+    }
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Value {
+            wait: *Flags::Wait & value != 0,
+            duration: *Flags::D & value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_int1_duration() {
+        let config: Value = Value::default();
+        assert_eq!(config.to_value(), 0x00);
+    }
+
+    #[test]
+    fn parse_int1_duration_from_u8_1() {
+        let config: Value = Value::from(0x8A);
+        assert_eq!(config, Value { wait: true, duration: 10 });
+        assert_eq!(config.to_value(), 0x8A);
+    }
+
+    #[test]
+    fn int1_duration_from_seconds() {
+        let config = Value::from_seconds(false, 0.05, &DataRateBandwidth::ODR_200_CUT_OFF_50);
+        assert_eq!(config.to_value(), 10);
+        assert_eq!(config.to_seconds(&DataRateBandwidth::ODR_200_CUT_OFF_50), 0.05);
+
+        let config = Value::from_seconds(true, 1.0, &DataRateBandwidth::ODR_800_CUT_OFF_30);
+        assert_eq!(config.to_value(), 0xFF);
+    }
+}
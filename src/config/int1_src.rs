@@ -0,0 +1,51 @@
+use bitmask::bitmask;
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        Ia = 0x40,
+        Zh = 0x20,
+        Zl = 0x10,
+        Yh = 0x08,
+        Yl = 0x04,
+        Xh = 0x02,
+        Xl = 0x01,
+    }
+}
+
+/// Decoded INT1_SRC, which is read-only. Reading it clears a latched interrupt.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Int1Source {
+    /// One or more interrupts have been generated
+    pub active: bool,
+    pub z_high: bool,
+    pub z_low: bool,
+    pub y_high: bool,
+    pub y_low: bool,
+    pub x_high: bool,
+    pub x_low: bool,
+}
+
+impl From<u8> for Int1Source {
+    fn from(value: u8) -> Self {
+        Int1Source {
+            active: *Flags::Ia & value != 0,
+            z_high: *Flags::Zh & value != 0,
+            z_low: *Flags::Zl & value != 0,
+            y_high: *Flags::Yh & value != 0,
+            y_low: *Flags::Yl & value != 0,
+            x_high: *Flags::Xh & value != 0,
+            x_low: *Flags::Xl & value != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_int1_src_from_u8_1() {
+        let source: Int1Source = Int1Source::from(0x62);
+        assert_eq!(source, Int1Source { active: true, z_high: true, x_high: true, ..Default::default() });
+    }
+}
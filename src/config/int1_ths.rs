@@ -0,0 +1,86 @@
+use crate::config::ctrlreg4::FullScaleSelection;
+
+// Thresholds are 15-bit, the MSB of each INT1_THS_xH is reserved
+const THRESHOLD_MASK: u16 = 0x7FFF;
+
+/// Interrupt thresholds for the three axes, in the same digits as the output registers. They
+/// span INT1_THS_XH to INT1_THS_ZL, high byte first.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Value {
+    pub x: u16,
+    pub y: u16,
+    pub z: u16,
+}
+
+fn dps_to_digits(dps: f32, full_scale: &FullScaleSelection) -> u16 {
+    let digits = dps * 1000.0 / full_scale.sensitivity_mdps();
+    // `as` saturates, so negative rates end up at 0 and large ones are clamped below
+    ((digits + 0.5) as u16).min(THRESHOLD_MASK)
+}
+
+impl Value {
+    /// Converts thresholds in degrees per second using the sensitivity of `full_scale`, which has
+    /// to match the full scale CTRL_REG4 is set to.
+    pub fn from_dps(x: f32, y: f32, z: f32, full_scale: &FullScaleSelection) -> Self {
+        Value {
+            x: dps_to_digits(x, full_scale),
+            y: dps_to_digits(y, full_scale),
+            z: dps_to_digits(z, full_scale),
+        }
+    }
+
+    pub fn to_dps(&self, full_scale: &FullScaleSelection) -> (f32, f32, f32) {
+        let sensitivity = full_scale.sensitivity_mdps() / 1000.0;
+        (self.x as f32 * sensitivity, self.y as f32 * sensitivity, self.z as f32 * sensitivity)
+    }
+
+    pub fn to_bytes(self) -> [u8; 6] {
+        let [xh, xl] = (self.x & THRESHOLD_MASK).to_be_bytes();
+        let [yh, yl] = (self.y & THRESHOLD_MASK).to_be_bytes();
+        let [zh, zl] = (self.z & THRESHOLD_MASK).to_be_bytes();
+        [xh, xl, yh, yl, zh, zl]
+    }
+}
+
+impl From<[u8; 6]> for Value {
+    fn from(value: [u8; 6]) -> Self {
+        Value {
+            x: u16::from_be_bytes([value[0], value[1]]) & THRESHOLD_MASK,
+            y: u16::from_be_bytes([value[2], value[3]]) & THRESHOLD_MASK,
+            z: u16::from_be_bytes([value[4], value[5]]) & THRESHOLD_MASK,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_int1_ths() {
+        let config: Value = Value::default();
+        assert_eq!(config.to_bytes(), [0; 6]);
+    }
+
+    #[test]
+    fn parse_int1_ths_1() {
+        let config: Value = Value { x: 0x1234, y: 0x7FFF, z: 0xFFFF };
+        assert_eq!(config.to_bytes(), [0x12, 0x34, 0x7F, 0xFF, 0x7F, 0xFF]);
+    }
+
+    #[test]
+    fn parse_int1_ths_from_bytes_1() {
+        let config: Value = Value::from([0x92, 0x34, 0x00, 0x01, 0x7F, 0xFE]);
+        assert_eq!(config, Value { x: 0x1234, y: 0x0001, z: 0x7FFE });
+    }
+
+    #[test]
+    fn int1_ths_from_dps() {
+        let config = Value::from_dps(70.0, 7.0, 0.0, &FullScaleSelection::FSS_2000_DPS);
+        assert_eq!(config, Value { x: 1000, y: 100, z: 0 });
+
+        let config = Value::from_dps(100.0, -5.0, 5000.0, &FullScaleSelection::FSS_250_DPS);
+        assert_eq!(config, Value { x: 11429, y: 0, z: 0x7FFF });
+        assert_eq!(Value { x: 1000, y: 0, z: 0 }.to_dps(&FullScaleSelection::FSS_500_DPS), (17.5, 0.0, 0.0));
+    }
+}
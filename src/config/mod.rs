@@ -1,5 +1,36 @@
+#![allow(clippy::init_numbered_fields)]
+
 pub mod ctrlreg1;
 pub mod ctrlreg2;
 pub mod ctrlreg3;
 pub mod ctrlreg4;
-pub mod ctrlreg5;
\ No newline at end of file
+pub mod ctrlreg5;
+pub mod fifo_ctrl;
+pub mod fifo_src;
+pub mod int1_cfg;
+pub mod int1_duration;
+pub mod int1_src;
+pub mod int1_ths;
+pub mod status_reg;
+
+/// All five control registers, as read back in a single burst by `L3G4200D::read_config`.
+#[derive(PartialEq, Debug)]
+pub struct Config {
+    pub ctrl_reg1: ctrlreg1::Value,
+    pub ctrl_reg2: ctrlreg2::Value,
+    pub ctrl_reg3: ctrlreg3::Value,
+    pub ctrl_reg4: ctrlreg4::Value,
+    pub ctrl_reg5: ctrlreg5::Value,
+}
+
+impl From<[u8; 5]> for Config {
+    fn from(value: [u8; 5]) -> Self {
+        Config {
+            ctrl_reg1: value[0].into(),
+            ctrl_reg2: value[1].into(),
+            ctrl_reg3: value[2].into(),
+            ctrl_reg4: value[3].into(),
+            ctrl_reg5: value[4].into(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use bitmask::bitmask;
+
+bitmask! {
+    mask Attrs: u8 where flags Flags {
+        Zyxor = 0x80,
+        Zor = 0x40,
+        Yor = 0x20,
+        Xor = 0x10,
+        Zyxda = 0x08,
+        Zda = 0x04,
+        Yda = 0x02,
+        Xda = 0x01,
+    }
+}
+
+/// Decoded STATUS_REG, which is read-only. The flags of an axis clear once its output registers
+/// are read.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Value {
+    /// A new sample overwrote one that was never read, on at least one axis
+    pub zyx_overrun: bool,
+    pub z_overrun: bool,
+    pub y_overrun: bool,
+    pub x_overrun: bool,
+    /// A new sample is available on all three axes
+    pub zyx_available: bool,
+    pub z_available: bool,
+    pub y_available: bool,
+    pub x_available: bool,
+}
+
+impl From<u8> for Value {
+    fn from(value: u8) -> Self {
+        Value {
+            zyx_overrun: *Flags::Zyxor & value != 0,
+            z_overrun: *Flags::Zor & value != 0,
+            y_overrun: *Flags::Yor & value != 0,
+            x_overrun: *Flags::Xor & value != 0,
+            zyx_available: *Flags::Zyxda & value != 0,
+            z_available: *Flags::Zda & value != 0,
+            y_available: *Flags::Yda & value != 0,
+            x_available: *Flags::Xda & value != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_status_reg_from_u8_1() {
+        let status: Value = Value::from(0x0F);
+        assert_eq!(status, Value { zyx_available: true, z_available: true, y_available: true, x_available: true, ..Default::default() });
+    }
+
+    #[test]
+    fn parse_status_reg_from_u8_2() {
+        let status: Value = Value::from(0xA2);
+        assert_eq!(status, Value { zyx_overrun: true, y_overrun: true, y_available: true, ..Default::default() });
+    }
+}
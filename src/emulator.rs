@@ -0,0 +1,589 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use embedded_hal::i2c::{self, NoAcknowledgeSource};
+use embedded_hal::spi;
+
+use crate::config::fifo_src::FIFO_DEPTH;
+use crate::gyro::{L3G4200D_ADDR, L3G4200D_DEV_ID};
+use crate::registers::L3G4200DRegister;
+
+const REGISTER_COUNT: usize = 0x40;
+
+const WHO_AM_I: u8      = L3G4200DRegister::WHO_AM_I.reg();
+const CTRL_REG1: u8     = L3G4200DRegister::CTRL_REG1.reg();
+const CTRL_REG2: u8     = L3G4200DRegister::CTRL_REG2.reg();
+const CTRL_REG3: u8     = L3G4200DRegister::CTRL_REG3.reg();
+const CTRL_REG4: u8     = L3G4200DRegister::CTRL_REG4.reg();
+const CTRL_REG5: u8     = L3G4200DRegister::CTRL_REG5.reg();
+const REFERENCE: u8     = L3G4200DRegister::REFERENCE.reg();
+const STATUS_REG: u8    = L3G4200DRegister::STATUS_REG.reg();
+const OUT_X_L: u8       = L3G4200DRegister::OUT_X_L.reg();
+const OUT_X_H: u8       = L3G4200DRegister::OUT_X_H.reg();
+const OUT_Y_H: u8       = L3G4200DRegister::OUT_Y_H.reg();
+const OUT_Z_H: u8       = L3G4200DRegister::OUT_Z_H.reg();
+const FIFO_CTRL: u8     = L3G4200DRegister::FIFO_CTRL.reg();
+const FIFO_SRC: u8      = L3G4200DRegister::FIFO_SRC.reg();
+const INT1_CFG: u8      = L3G4200DRegister::INT1_CFG.reg();
+const INT1_THS_XH: u8   = L3G4200DRegister::INT1_THS_XH.reg();
+const INT1_THS_XL: u8   = L3G4200DRegister::INT1_THS_XL.reg();
+const INT1_THS_YH: u8   = L3G4200DRegister::INT1_THS_YH.reg();
+const INT1_THS_YL: u8   = L3G4200DRegister::INT1_THS_YL.reg();
+const INT1_THS_ZH: u8   = L3G4200DRegister::INT1_THS_ZH.reg();
+const INT1_THS_ZL: u8   = L3G4200DRegister::INT1_THS_ZL.reg();
+const INT1_DURATION: u8 = L3G4200DRegister::INT1_DURATION.reg();
+
+const CTRL_REG1_PD: u8 = 0x08;
+const CTRL_REG4_BLE: u8 = 0x40;
+const CTRL_REG5_BOOT: u8 = 0x80;
+const CTRL_REG5_FIFO_EN: u8 = 0x40;
+
+const FIFO_CTRL_MODE: u8 = 0xE0;
+const FIFO_CTRL_WTM: u8 = 0x1F;
+const FIFO_MODE_FIFO: u8 = 0x20;
+const FIFO_MODE_STREAM: u8 = 0x40;
+const FIFO_MODE_STREAM_TO_FIFO: u8 = 0x60;
+
+const FIFO_SRC_WTM: u8 = 0x80;
+const FIFO_SRC_OVRN: u8 = 0x40;
+const FIFO_SRC_EMPTY: u8 = 0x20;
+
+const STATUS_DATA_AVAILABLE: u8 = 0x0F;
+const STATUS_ZYXDA: u8 = 0x08;
+const STATUS_ZYXOR: u8 = 0x80;
+
+const I2C_AUTO_INCREMENT: u8 = 0x80;
+const SPI_READ: u8 = 0x80;
+const SPI_MULTI_BYTE: u8 = 0x40;
+const SPI_ADDRESS_MASK: u8 = 0x3F;
+
+/// Bits the host can change in each register; reserved bits and read-only registers are 0.
+fn writable_bits(reg: u8) -> u8 {
+    match reg {
+        CTRL_REG1 | CTRL_REG3 | REFERENCE | FIFO_CTRL | INT1_CFG => 0xFF,
+        CTRL_REG2 => 0x3F,
+        CTRL_REG4 => 0xF7,
+        CTRL_REG5 => 0xDF,
+        INT1_THS_XH | INT1_THS_YH | INT1_THS_ZH => 0x7F,
+        INT1_THS_XL | INT1_THS_YL | INT1_THS_ZL | INT1_DURATION => 0xFF,
+        _ => 0x00,
+    }
+}
+
+fn reset_value(reg: u8) -> u8 {
+    match reg {
+        WHO_AM_I => L3G4200D_DEV_ID,
+        CTRL_REG1 => 0x07,
+        _ => 0x00,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorError {
+    /// The I2C transaction was addressed to a different slave address.
+    NoAcknowledge,
+}
+
+impl i2c::Error for EmulatorError {
+    fn kind(&self) -> i2c::ErrorKind {
+        i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+    }
+}
+
+impl spi::Error for EmulatorError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FifoBehaviour {
+    /// Stops collecting once full
+    Fifo,
+    /// Overwrites the oldest sample once full
+    Stream,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SpiPhase {
+    Address,
+    Data { reg: u8, read: bool, multi_byte: bool },
+}
+
+#[derive(Debug)]
+struct RegisterFile {
+    registers: [u8; REGISTER_COUNT],
+    address: u8,
+    pointer: u8,
+    samples: VecDeque<[i16; 3]>,
+    fifo: VecDeque<[i16; 3]>,
+    fifo_overrun: bool,
+    ignored_writes: Vec<(u8, u8)>,
+}
+
+impl RegisterFile {
+    fn new(address: u8) -> Self {
+        let mut registers = [0u8; REGISTER_COUNT];
+        for (reg, value) in registers.iter_mut().enumerate() {
+            *value = reset_value(reg as u8);
+        }
+        let mut file = RegisterFile {
+            registers,
+            address,
+            pointer: 0,
+            samples: VecDeque::new(),
+            fifo: VecDeque::new(),
+            fifo_overrun: false,
+            ignored_writes: Vec::new(),
+        };
+        file.update_fifo_src();
+        file
+    }
+
+    /// Trigger-based modes are not emulated, so they behave as they would before the trigger.
+    fn fifo_behaviour(&self) -> Option<FifoBehaviour> {
+        if self.registers[CTRL_REG5 as usize] & CTRL_REG5_FIFO_EN == 0 {
+            return None;
+        }
+        match self.registers[FIFO_CTRL as usize] & FIFO_CTRL_MODE {
+            FIFO_MODE_FIFO => Some(FifoBehaviour::Fifo),
+            FIFO_MODE_STREAM | FIFO_MODE_STREAM_TO_FIFO => Some(FifoBehaviour::Stream),
+            _ => None,
+        }
+    }
+
+    fn update_fifo_src(&mut self) {
+        let len = self.fifo.len();
+        let watermark = (self.registers[FIFO_CTRL as usize] & FIFO_CTRL_WTM) as usize;
+        let mut value = len.min(FIFO_DEPTH - 1) as u8;
+        if watermark > 0 && len >= watermark {
+            value |= FIFO_SRC_WTM;
+        }
+        if self.fifo_overrun {
+            value |= FIFO_SRC_OVRN;
+        }
+        if len == 0 {
+            value |= FIFO_SRC_EMPTY;
+        }
+        self.registers[FIFO_SRC as usize] = value;
+    }
+
+    fn load_outputs(&mut self, sample: [i16; 3]) {
+        let big_endian = self.registers[CTRL_REG4 as usize] & CTRL_REG4_BLE != 0;
+        for (axis, value) in sample.iter().enumerate() {
+            let bytes = if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+            let reg = OUT_X_L as usize + 2 * axis;
+            self.registers[reg] = bytes[0];
+            self.registers[reg + 1] = bytes[1];
+        }
+    }
+
+    /// Sub-address that follows `reg` during an auto-incremented burst.
+    fn next_address(&self, reg: u8) -> u8 {
+        if reg == OUT_Z_H && self.fifo_behaviour().is_some() {
+            OUT_X_L
+        } else {
+            (reg + 1) & SPI_ADDRESS_MASK
+        }
+    }
+
+    fn read(&mut self, reg: u8) -> u8 {
+        let reg = reg & SPI_ADDRESS_MASK;
+        let value = self.registers[reg as usize];
+
+        // Reading the second byte of an axis consumes its sample
+        let consumed = match reg {
+            OUT_X_H => 0x11,
+            OUT_Y_H => 0x22,
+            OUT_Z_H => 0x44,
+            _ => 0x00,
+        };
+        if consumed != 0 {
+            let status = &mut self.registers[STATUS_REG as usize];
+            *status &= !consumed;
+            if *status & 0x07 == 0 {
+                *status &= !(STATUS_ZYXDA | STATUS_ZYXOR);
+            }
+        }
+
+        // Finishing a FIFO entry brings the next one into the output registers
+        if reg == OUT_Z_H && self.fifo_behaviour().is_some() && self.fifo.pop_front().is_some() {
+            self.fifo_overrun = false;
+            if let Some(&next) = self.fifo.front() {
+                self.load_outputs(next);
+                self.registers[STATUS_REG as usize] |= STATUS_DATA_AVAILABLE;
+            }
+            self.update_fifo_src();
+        }
+        value
+    }
+
+    fn write(&mut self, reg: u8, value: u8) {
+        let reg = reg & SPI_ADDRESS_MASK;
+        let mask = writable_bits(reg);
+        if value & !mask != 0 || mask == 0 {
+            self.ignored_writes.push((reg, value));
+        }
+        let current = self.registers[reg as usize];
+        self.registers[reg as usize] = (current & !mask) | (value & mask);
+
+        // BOOT clears itself once the trimming parameters are reloaded
+        if reg == CTRL_REG5 {
+            self.registers[reg as usize] &= !CTRL_REG5_BOOT;
+        }
+
+        // Going back to Bypass mode empties the FIFO
+        if (reg == CTRL_REG5 || reg == FIFO_CTRL) && self.fifo_behaviour().is_none() {
+            self.fifo.clear();
+            self.fifo_overrun = false;
+        }
+        if reg == CTRL_REG5 || reg == FIFO_CTRL {
+            self.update_fifo_src();
+        }
+    }
+
+    fn latch(&mut self) -> bool {
+        if self.registers[CTRL_REG1 as usize] & CTRL_REG1_PD == 0 {
+            return false;
+        }
+        let Some(sample) = self.samples.pop_front() else {
+            return false;
+        };
+
+        if let Some(behaviour) = self.fifo_behaviour() {
+            if self.fifo.len() == FIFO_DEPTH {
+                self.fifo_overrun = true;
+                if behaviour == FifoBehaviour::Fifo {
+                    self.update_fifo_src();
+                    return true;
+                }
+                self.fifo.pop_front();
+            }
+            self.fifo.push_back(sample);
+            if let Some(&front) = self.fifo.front() {
+                self.load_outputs(front);
+            }
+            self.registers[STATUS_REG as usize] |= STATUS_DATA_AVAILABLE;
+            self.update_fifo_src();
+            return true;
+        }
+
+        // Any axis whose previous sample was never read gets flagged as overrun
+        let status = self.registers[STATUS_REG as usize];
+        self.registers[STATUS_REG as usize] = STATUS_DATA_AVAILABLE | (status & 0xF0) | ((status & STATUS_DATA_AVAILABLE) << 4);
+        self.load_outputs(sample);
+        true
+    }
+
+    fn begin_transaction(&mut self) {
+        if self.fifo_behaviour().is_some() {
+            while self.latch() {}
+        } else if self.registers[STATUS_REG as usize] & STATUS_ZYXDA == 0 {
+            self.latch();
+        }
+    }
+}
+
+/// In-memory L3G4200D that can be plugged in anywhere an embedded-hal I2C bus or SPI device is
+/// expected.
+///
+/// Clones share the same register file, so a test can hand one to the driver and keep another to
+/// script samples and inspect registers. Queued samples are latched into the output registers
+/// whenever the previous one has been read (or on `tick`), as long as CTRL_REG1 is out of
+/// power-down, setting and clearing STATUS_REG the way the sensor does. With the FIFO enabled,
+/// every queued sample is pushed into it at the start of the next transaction instead.
+#[derive(Debug, Clone)]
+pub struct Emulator {
+    state: Rc<RefCell<RegisterFile>>,
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        Self::with_address(L3G4200D_ADDR)
+    }
+
+    pub fn with_address(address: u8) -> Self {
+        Emulator { state: Rc::new(RefCell::new(RegisterFile::new(address))) }
+    }
+
+    /// Puts every register back to its power-on value and drops queued samples.
+    pub fn reset(&self) {
+        let mut state = self.state.borrow_mut();
+        *state = RegisterFile::new(state.address);
+    }
+
+    /// Current value of a register, without the side effects of a bus read.
+    pub fn register(&self, reg: L3G4200DRegister) -> u8 {
+        self.state.borrow().registers[reg.reg() as usize]
+    }
+
+    /// Overwrites a register, bypassing write protection (e.g. to stage OUT_TEMP).
+    pub fn set_register(&self, reg: L3G4200DRegister, value: u8) {
+        self.state.borrow_mut().registers[reg.reg() as usize] = value;
+    }
+
+    pub fn push_sample(&self, sample: [i16; 3]) {
+        self.state.borrow_mut().samples.push_back(sample);
+    }
+
+    pub fn push_samples<I: IntoIterator<Item = [i16; 3]>>(&self, samples: I) {
+        self.state.borrow_mut().samples.extend(samples);
+    }
+
+    pub fn pending_samples(&self) -> usize {
+        self.state.borrow().samples.len()
+    }
+
+    /// Latches the next queued sample even if the current one was not read yet, returning whether
+    /// there was one to latch.
+    pub fn tick(&self) -> bool {
+        self.state.borrow_mut().latch()
+    }
+
+    /// Writes that touched read-only registers or reserved bits, as `(register, value)`.
+    pub fn ignored_writes(&self) -> Vec<(u8, u8)> {
+        self.state.borrow().ignored_writes.clone()
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl i2c::ErrorType for Emulator {
+    type Error = EmulatorError;
+}
+
+impl i2c::I2c for Emulator {
+    fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        if address != state.address {
+            return Err(EmulatorError::NoAcknowledge);
+        }
+        state.begin_transaction();
+
+        let mut auto_increment = false;
+        let mut sub_address_sent = false;
+        for operation in operations.iter_mut() {
+            match operation {
+                i2c::Operation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        if !sub_address_sent {
+                            state.pointer = byte & !I2C_AUTO_INCREMENT;
+                            auto_increment = byte & I2C_AUTO_INCREMENT != 0;
+                            sub_address_sent = true;
+                        } else {
+                            let reg = state.pointer;
+                            state.write(reg, *byte);
+                            if auto_increment {
+                                state.pointer = state.next_address(reg);
+                            }
+                        }
+                    }
+                }
+                i2c::Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        let reg = state.pointer;
+                        *byte = state.read(reg);
+                        if auto_increment {
+                            state.pointer = state.next_address(reg);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl spi::ErrorType for Emulator {
+    type Error = EmulatorError;
+}
+
+impl spi::SpiDevice for Emulator {
+    fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        state.begin_transaction();
+
+        let mut phase = SpiPhase::Address;
+        let mut exchange = |state: &mut RegisterFile, tx: u8| -> u8 {
+            match phase {
+                SpiPhase::Address => {
+                    phase = SpiPhase::Data {
+                        reg: tx & SPI_ADDRESS_MASK,
+                        read: tx & SPI_READ != 0,
+                        multi_byte: tx & SPI_MULTI_BYTE != 0,
+                    };
+                    0xFF
+                }
+                SpiPhase::Data { reg, read, multi_byte } => {
+                    let rx = if read { state.read(reg) } else { state.write(reg, tx); 0xFF };
+                    if multi_byte {
+                        phase = SpiPhase::Data { reg: state.next_address(reg), read, multi_byte };
+                    }
+                    rx
+                }
+            }
+        };
+
+        for operation in operations.iter_mut() {
+            match operation {
+                spi::Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = exchange(&mut state, 0x00);
+                    }
+                }
+                spi::Operation::Write(bytes) => {
+                    for byte in bytes.iter() {
+                        exchange(&mut state, *byte);
+                    }
+                }
+                spi::Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let rx = exchange(&mut state, write.get(i).copied().unwrap_or(0x00));
+                        if let Some(byte) = read.get_mut(i) {
+                            *byte = rx;
+                        }
+                    }
+                }
+                spi::Operation::TransferInPlace(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = exchange(&mut state, *byte);
+                    }
+                }
+                spi::Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Emulator {
+    async fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        i2c::I2c::transaction(self, address, operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice for Emulator {
+    async fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        spi::SpiDevice::transaction(self, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::I2c;
+    use embedded_hal::spi::SpiDevice;
+
+    #[test]
+    fn reset_values() {
+        let emulator = Emulator::new();
+        assert_eq!(emulator.register(L3G4200DRegister::WHO_AM_I), 0xD3);
+        assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG1), 0x07);
+        assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG4), 0x00);
+    }
+
+    #[test]
+    fn read_only_and_reserved_bits_are_ignored() {
+        let mut emulator = Emulator::new();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[WHO_AM_I, 0x00]).unwrap();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG2, 0xFF]).unwrap();
+        assert_eq!(emulator.register(L3G4200DRegister::WHO_AM_I), 0xD3);
+        assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG2), 0x3F);
+        assert_eq!(emulator.ignored_writes(), vec![(WHO_AM_I, 0x00), (CTRL_REG2, 0xFF)]);
+    }
+
+    #[test]
+    fn wrong_address_is_not_acknowledged() {
+        let mut emulator = Emulator::new();
+        let mut buffer = [0u8];
+        assert_eq!(emulator.write_read(0x68, &[WHO_AM_I], &mut buffer), Err(EmulatorError::NoAcknowledge));
+    }
+
+    #[test]
+    fn i2c_auto_increment() {
+        let mut emulator = Emulator::new();
+        let mut buffer = [0u8; 2];
+        emulator.write_read(L3G4200D_ADDR, &[CTRL_REG1], &mut buffer).unwrap();
+        assert_eq!(buffer, [0x07, 0x07], "Without the MSB the sub-address should not move");
+        emulator.write_read(L3G4200D_ADDR, &[I2C_AUTO_INCREMENT | WHO_AM_I], &mut buffer).unwrap();
+        assert_eq!(buffer, [0xD3, 0x00]);
+    }
+
+    #[test]
+    fn spi_multi_byte_write() {
+        let mut emulator = Emulator::new();
+        SpiDevice::write(&mut emulator, &[SPI_MULTI_BYTE | CTRL_REG1, 0x0F, 0x01]).unwrap();
+        assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG1), 0x0F);
+        assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG2), 0x01);
+    }
+
+    #[test]
+    fn samples_latch_only_when_powered_up() {
+        let mut emulator = Emulator::new();
+        emulator.push_sample([1, 2, 3]);
+        assert!(!emulator.tick(), "Power-down mode should not produce data");
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG1, 0x0F]).unwrap();
+        assert!(emulator.tick());
+        assert_eq!(emulator.register(L3G4200DRegister::STATUS_REG), 0x0F);
+        assert_eq!(emulator.register(L3G4200DRegister::OUT_Y_L), 2);
+    }
+
+    #[test]
+    fn fifo_src_reset_value() {
+        let emulator = Emulator::new();
+        assert_eq!(emulator.register(L3G4200DRegister::FIFO_SRC), FIFO_SRC_EMPTY);
+    }
+
+    #[test]
+    fn fifo_mode_stops_when_full() {
+        let mut emulator = Emulator::new();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG1, 0x0F]).unwrap();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG5, CTRL_REG5_FIFO_EN]).unwrap();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[FIFO_CTRL, FIFO_MODE_FIFO]).unwrap();
+        emulator.push_samples((0..40).map(|i| [i, 0, 0]));
+        while emulator.tick() {}
+        assert_eq!(emulator.register(L3G4200DRegister::FIFO_SRC), FIFO_SRC_OVRN | 0x1F);
+
+        let mut buffer = [0u8; 12];
+        emulator.write_read(L3G4200D_ADDR, &[I2C_AUTO_INCREMENT | OUT_X_L], &mut buffer).unwrap();
+        assert_eq!(buffer, [0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0], "Burst should roll over to the next sample");
+        assert_eq!(emulator.register(L3G4200DRegister::FIFO_SRC), 30);
+    }
+
+    #[test]
+    fn stream_mode_keeps_newest_samples() {
+        let mut emulator = Emulator::new();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG1, 0x0F]).unwrap();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG5, CTRL_REG5_FIFO_EN]).unwrap();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[FIFO_CTRL, FIFO_MODE_STREAM | 16]).unwrap();
+        emulator.push_samples((0..40).map(|i| [i, 0, 0]));
+        while emulator.tick() {}
+        assert_eq!(emulator.register(L3G4200DRegister::FIFO_SRC), FIFO_SRC_WTM | FIFO_SRC_OVRN | 0x1F);
+        assert_eq!(emulator.register(L3G4200DRegister::OUT_X_L), 8);
+
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[FIFO_CTRL, 0x00]).unwrap();
+        assert_eq!(emulator.register(L3G4200DRegister::FIFO_SRC), FIFO_SRC_EMPTY, "Bypass mode should reset the FIFO");
+    }
+
+    #[test]
+    fn unread_sample_sets_overrun() {
+        let mut emulator = Emulator::new();
+        I2c::write(&mut emulator, L3G4200D_ADDR, &[CTRL_REG1, 0x0F]).unwrap();
+        emulator.push_samples([[1, 1, 1], [2, 2, 2]]);
+        emulator.tick();
+        emulator.tick();
+        assert_eq!(emulator.register(L3G4200DRegister::STATUS_REG), 0xFF);
+
+        let mut buffer = [0u8; 6];
+        emulator.write_read(L3G4200D_ADDR, &[I2C_AUTO_INCREMENT | OUT_X_L], &mut buffer).unwrap();
+        assert_eq!(buffer, [2, 0, 2, 0, 2, 0]);
+        assert_eq!(emulator.register(L3G4200DRegister::STATUS_REG), 0x00);
+    }
+}
@@ -1,12 +1,21 @@
 use thiserror::Error;
-use i2cdev::linux::LinuxI2CError;
 
 #[derive(Debug, Error)]
-pub enum L3G4200DError {
+pub enum L3G4200DError<E> {
     #[error("Register is not writable")]
     NotWritableRegister(),
     #[error("Attempt to write to protected bits")]
     WriteProtectedRegister(),
-    #[error("I2C error")]
-    I2CError(#[from] LinuxI2CError),
+    #[error("No L3G4200D found on the bus")]
+    DeviceNotFound(),
+    #[error("Timed out waiting for the device")]
+    Timeout(),
+    #[error("Device moved during calibration")]
+    DeviceMoved(),
+    #[error("Not enough samples to measure")]
+    NotEnoughSamples(),
+    #[error("No temperature reference set")]
+    NoTemperatureReference(),
+    #[error("Bus error")]
+    BusError(E),
 }
\ No newline at end of file
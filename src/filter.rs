@@ -0,0 +1,354 @@
+use core::f32::consts::PI;
+
+use libm::{cosf, sinf};
+
+use crate::config::ctrlreg1::DataRateBandwidth;
+
+/// Stateful filter applied to successive samples of the three axes. Each axis keeps its own
+/// history.
+pub trait Filter {
+    /// Feeds one sample and returns the filtered value of each axis.
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3];
+
+    /// Forgets every sample seen so far.
+    fn reset(&mut self);
+
+    /// Builds a pipeline feeding the output of this filter into `next`.
+    fn then<F: Filter>(self, next: F) -> Chain<Self, F>
+    where
+        Self: Sized,
+    {
+        Chain { first: self, second: next }
+    }
+}
+
+impl<F: Filter + ?Sized> Filter for &mut F {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        (**self).apply(sample)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Two filters run one after the other, see `Filter::then`.
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Filter, B: Filter> Filter for Chain<A, B> {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        self.second.apply(self.first.apply(sample))
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+/// Mean of the last `N` samples, or of every sample until `N` were seen.
+#[derive(Debug, Clone)]
+pub struct MovingAverage<const N: usize> {
+    window: [[f32; 3]; N],
+    next: usize,
+    count: usize,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    pub fn new() -> Self {
+        const { assert!(N > 0, "MovingAverage needs a window of at least one sample") };
+        MovingAverage { window: [[0.0; 3]; N], next: 0, count: 0 }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        self.window[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.count = (self.count + 1).min(N);
+        // Summing the window again each time avoids accumulating rounding errors
+        let window = &self.window[..self.count];
+        [0, 1, 2].map(|axis| window.iter().map(|sample| sample[axis]).sum::<f32>() / self.count as f32)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Exponential smoothing: `output = alpha * sample + (1 - alpha) * previous output`. The first
+/// sample goes through unchanged.
+#[derive(Debug, Clone)]
+pub struct ExponentialSmoothing {
+    alpha: f32,
+    state: Option<[f32; 3]>,
+}
+
+impl ExponentialSmoothing {
+    /// `alpha` is clamped to 0..=1. Lower values smooth more.
+    pub fn new(alpha: f32) -> Self {
+        ExponentialSmoothing { alpha: alpha.clamp(0.0, 1.0), state: None }
+    }
+}
+
+impl Filter for ExponentialSmoothing {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        let output = match self.state {
+            Some(previous) => [0, 1, 2].map(|axis| self.alpha * sample[axis] + (1.0 - self.alpha) * previous[axis]),
+            None => sample,
+        };
+        self.state = Some(output);
+        output
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Median of the last `N` samples, which removes isolated spikes. Until `N` samples were seen,
+/// the median of those available.
+#[derive(Debug, Clone)]
+pub struct Median<const N: usize> {
+    window: [[f32; 3]; N],
+    next: usize,
+    count: usize,
+}
+
+impl<const N: usize> Median<N> {
+    pub fn new() -> Self {
+        const { assert!(N > 0, "Median needs a window of at least one sample") };
+        Median { window: [[0.0; 3]; N], next: 0, count: 0 }
+    }
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for Median<N> {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        self.window[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.count = (self.count + 1).min(N);
+        [0, 1, 2].map(|axis| {
+            let mut values = [0.0; N];
+            for (value, sample) in values.iter_mut().zip(&self.window[..self.count]) {
+                *value = sample[axis];
+            }
+            let values = &mut values[..self.count];
+            values.sort_unstable_by(f32::total_cmp);
+            let middle = self.count / 2;
+            if self.count % 2 == 0 {
+                (values[middle - 1] + values[middle]) / 2.0
+            } else {
+                values[middle]
+            }
+        })
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Second order IIR section, normalized so that `a0` is 1.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BiquadCoefficients {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl BiquadCoefficients {
+    // Designs from the Audio EQ Cookbook (R. Bristow-Johnson), at the output data rate
+    fn design(data_rate: &DataRateBandwidth, frequency_hz: f32, q: f32, b: impl Fn(f32) -> [f32; 3]) -> Self {
+        let w0 = 2.0 * PI * frequency_hz / data_rate.output_data_rate_hz();
+        let alpha = sinf(w0) / (2.0 * q);
+        let cos_w0 = cosf(w0);
+        let [b0, b1, b2] = b(cos_w0);
+        let a0 = 1.0 + alpha;
+        BiquadCoefficients { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: -2.0 * cos_w0 / a0, a2: (1.0 - alpha) / a0 }
+    }
+
+    pub fn low_pass(data_rate: &DataRateBandwidth, cutoff_hz: f32, q: f32) -> Self {
+        Self::design(data_rate, cutoff_hz, q, |cos_w0| [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0])
+    }
+
+    pub fn high_pass(data_rate: &DataRateBandwidth, cutoff_hz: f32, q: f32) -> Self {
+        Self::design(data_rate, cutoff_hz, q, |cos_w0| [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0])
+    }
+
+    pub fn notch(data_rate: &DataRateBandwidth, center_hz: f32, q: f32) -> Self {
+        Self::design(data_rate, center_hz, q, |cos_w0| [1.0, -2.0 * cos_w0, 1.0])
+    }
+}
+
+/// Butterworth response, for `q` in the biquad designs.
+pub const BUTTERWORTH_Q: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+/// Biquad IIR filter (transposed direct form II). Design the coefficients for the output data
+/// rate the samples come at, e.g. `L3G4200D::data_rate`.
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    coefficients: BiquadCoefficients,
+    state: [[f32; 2]; 3],
+}
+
+impl Biquad {
+    pub fn new(coefficients: BiquadCoefficients) -> Self {
+        Biquad { coefficients, state: [[0.0; 2]; 3] }
+    }
+
+    pub fn low_pass(data_rate: &DataRateBandwidth, cutoff_hz: f32) -> Self {
+        Self::new(BiquadCoefficients::low_pass(data_rate, cutoff_hz, BUTTERWORTH_Q))
+    }
+
+    pub fn high_pass(data_rate: &DataRateBandwidth, cutoff_hz: f32) -> Self {
+        Self::new(BiquadCoefficients::high_pass(data_rate, cutoff_hz, BUTTERWORTH_Q))
+    }
+
+    pub fn notch(data_rate: &DataRateBandwidth, center_hz: f32, q: f32) -> Self {
+        Self::new(BiquadCoefficients::notch(data_rate, center_hz, q))
+    }
+
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        self.coefficients
+    }
+}
+
+impl Filter for Biquad {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        let mut output = [0.0; 3];
+        for ((output, state), input) in output.iter_mut().zip(self.state.iter_mut()).zip(sample) {
+            *output = b0 * input + state[0];
+            state[0] = b1 * input - a1 * *output + state[1];
+            state[1] = b2 * input - a2 * *output;
+        }
+        output
+    }
+
+    fn reset(&mut self) {
+        self.state = [[0.0; 2]; 3];
+    }
+}
+
+/// Zeroes values whose magnitude is at most `threshold`, e.g. noise at rest. Stateless.
+#[derive(Debug, Clone)]
+pub struct Deadband {
+    threshold: f32,
+}
+
+impl Deadband {
+    pub fn new(threshold: f32) -> Self {
+        Deadband { threshold }
+    }
+}
+
+impl Filter for Deadband {
+    fn apply(&mut self, sample: [f32; 3]) -> [f32; 3] {
+        sample.map(|value| if value.abs() <= self.threshold { 0.0 } else { value })
+    }
+
+    fn reset(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output after feeding a sine of `frequency_hz` long enough for the transient to die out
+    fn sine_amplitude<F: Filter>(filter: &mut F, frequency_hz: f32, sample_rate_hz: f32) -> f32 {
+        let mut amplitude: f32 = 0.0;
+        for step in 0..2000 {
+            let value = sinf(2.0 * PI * frequency_hz * step as f32 / sample_rate_hz);
+            let output = filter.apply([value, 0.0, 0.0])[0];
+            if step >= 1000 {
+                amplitude = amplitude.max(output.abs());
+            }
+        }
+        amplitude
+    }
+
+    #[test]
+    fn moving_average() {
+        let mut filter = MovingAverage::<3>::new();
+        assert_eq!(filter.apply([3.0, 0.0, -3.0]), [3.0, 0.0, -3.0]);
+        assert_eq!(filter.apply([6.0, 0.0, -6.0]), [4.5, 0.0, -4.5]);
+        filter.apply([9.0, 3.0, 0.0]);
+        assert_eq!(filter.apply([12.0, 3.0, 0.0]), [9.0, 2.0, -2.0]);
+        filter.reset();
+        assert_eq!(filter.apply([1.0, 1.0, 1.0]), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn exponential_smoothing() {
+        let mut filter = ExponentialSmoothing::new(0.25);
+        assert_eq!(filter.apply([8.0, 0.0, 4.0]), [8.0, 0.0, 4.0]);
+        assert_eq!(filter.apply([0.0, 8.0, 4.0]), [6.0, 2.0, 4.0]);
+        filter.reset();
+        assert_eq!(filter.apply([1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn median_removes_spikes() {
+        let mut filter = Median::<3>::new();
+        assert_eq!(filter.apply([1.0, 5.0, 0.0]), [1.0, 5.0, 0.0]);
+        assert_eq!(filter.apply([2.0, 7.0, 0.0]), [1.5, 6.0, 0.0]);
+        assert_eq!(filter.apply([1000.0, 6.0, 0.0]), [2.0, 6.0, 0.0]);
+        assert_eq!(filter.apply([3.0, -500.0, 0.0]), [3.0, 6.0, 0.0]);
+    }
+
+    #[test]
+    fn biquad_responses() {
+        let data_rate = DataRateBandwidth::ODR_800_CUT_OFF_30;
+        let low_pass = || Biquad::low_pass(&data_rate, 20.0);
+        assert!((sine_amplitude(&mut low_pass(), 2.0, 800.0) - 1.0).abs() < 0.01);
+        assert!((sine_amplitude(&mut low_pass(), 20.0, 800.0) - BUTTERWORTH_Q).abs() < 0.01);
+        assert!(sine_amplitude(&mut low_pass(), 200.0, 800.0) < 0.02);
+
+        let high_pass = || Biquad::high_pass(&data_rate, 20.0);
+        assert!(sine_amplitude(&mut high_pass(), 1.0, 800.0) < 0.01);
+        assert!((sine_amplitude(&mut high_pass(), 200.0, 800.0) - 1.0).abs() < 0.01);
+
+        let notch = || Biquad::notch(&data_rate, 50.0, 2.0);
+        assert!(sine_amplitude(&mut notch(), 50.0, 800.0) < 0.01);
+        assert!((sine_amplitude(&mut notch(), 5.0, 800.0) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn biquad_axes_are_independent() {
+        let mut filter = Biquad::low_pass(&DataRateBandwidth::ODR_100_CUT_OFF_12_5, 5.0);
+        for _ in 0..500 {
+            filter.apply([10.0, 0.0, -2.0]);
+        }
+        let [x, y, z] = filter.apply([10.0, 0.0, -2.0]);
+        assert!((x - 10.0).abs() < 1e-3 && y == 0.0 && (z + 2.0).abs() < 1e-3);
+        filter.reset();
+        assert!(filter.apply([10.0, 0.0, 0.0])[0] < 1.0);
+    }
+
+    #[test]
+    fn deadband_and_chain() {
+        let mut pipeline = Median::<3>::new().then(Deadband::new(0.5)).then(ExponentialSmoothing::new(0.5));
+        assert_eq!(pipeline.apply([0.4, 2.0, -0.6]), [0.0, 2.0, -0.6]);
+        assert_eq!(pipeline.apply([0.4, 4.0, -0.6]), [0.0, 2.5, -0.6]);
+        pipeline.reset();
+        assert_eq!(pipeline.apply([1.0, 1.0, 1.0]), [1.0, 1.0, 1.0]);
+    }
+}
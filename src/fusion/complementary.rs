@@ -0,0 +1,189 @@
+use libm::{atan2f, cosf, sinf, sqrtf, tanf};
+
+use crate::gyro::L3G4200D;
+use crate::interface::Interface;
+
+use super::{read_inputs, Accelerometer, FusionError};
+
+/// Pitch and roll from the direction of gravity, in radians (Z-Y-X order). `None` for a zero
+/// vector, e.g. in free fall.
+pub fn tilt_from_acceleration(acceleration: [f32; 3]) -> Option<(f32, f32)> {
+    let [x, y, z] = acceleration;
+    if x == 0.0 && y == 0.0 && z == 0.0 {
+        return None;
+    }
+    Some((atan2f(-x, sqrtf(y * y + z * z)), atan2f(y, z)))
+}
+
+/// Blends pitch and roll integrated from the gyroscope, which are smooth but drift, with those
+/// given by the accelerometer, which do not drift but are disturbed by any linear acceleration.
+///
+/// `time_constant` (in seconds) is where the two cross over: disturbances shorter than it are
+/// followed from the gyroscope, and drift over longer periods is pulled back by the
+/// accelerometer. Yaw cannot be observed from gravity and is not estimated.
+#[derive(Debug, Clone)]
+pub struct ComplementaryFilter {
+    time_constant: f32,
+    // Radians, None until the first accelerometer reading
+    angles: Option<(f32, f32)>,
+    previous_timestamp: Option<f64>,
+}
+
+impl ComplementaryFilter {
+    pub fn new(time_constant: f32) -> Self {
+        ComplementaryFilter { time_constant, angles: None, previous_timestamp: None }
+    }
+
+    pub fn time_constant(&self) -> f32 {
+        self.time_constant
+    }
+
+    pub fn set_time_constant(&mut self, time_constant: f32) {
+        self.time_constant = time_constant;
+    }
+
+    /// Applies a rate in dps held for `dt` seconds, and the acceleration at the end of it. The
+    /// first update starts from the accelerometer tilt.
+    pub fn update(&mut self, rate_dps: (f32, f32, f32), acceleration: [f32; 3], dt: f32) {
+        let measured = tilt_from_acceleration(acceleration);
+        let Some((pitch, roll)) = self.angles else {
+            self.angles = measured;
+            return;
+        };
+        if dt <= 0.0 {
+            return;
+        }
+        // Body rates to Euler angle rates
+        let (p, q, r) = (rate_dps.0.to_radians(), rate_dps.1.to_radians(), rate_dps.2.to_radians());
+        let roll_rate = p + (q * sinf(roll) + r * cosf(roll)) * tanf(pitch);
+        let pitch_rate = q * cosf(roll) - r * sinf(roll);
+        let predicted = (pitch + pitch_rate * dt, roll + roll_rate * dt);
+
+        let alpha = self.time_constant / (self.time_constant + dt);
+        // Roll is blended across ±180° and kept in that range
+        self.angles = Some(match measured {
+            Some((measured_pitch, measured_roll)) => (
+                alpha * predicted.0 + (1.0 - alpha) * measured_pitch,
+                wrap(predicted.1 + (1.0 - alpha) * wrap(measured_roll - predicted.1)),
+            ),
+            None => (predicted.0, wrap(predicted.1)),
+        });
+    }
+
+    /// Same as `update`, with samples taken at `timestamp` seconds. The first call only starts
+    /// the filter, and timestamps that do not move forward are ignored.
+    pub fn update_at(&mut self, rate_dps: (f32, f32, f32), acceleration: [f32; 3], timestamp: f64) {
+        match self.previous_timestamp {
+            Some(previous) if timestamp <= previous => return,
+            Some(previous) => self.update(rate_dps, acceleration, (timestamp - previous) as f32),
+            None => self.update(rate_dps, acceleration, 0.0),
+        }
+        self.previous_timestamp = Some(timestamp);
+    }
+
+    /// Reads the rate from `gyro` and the acceleration from `accelerometer`, both taken at
+    /// `timestamp` seconds, and updates the estimate. Returns pitch and roll in degrees.
+    pub fn update_from<DI: Interface, A: Accelerometer>(&mut self, gyro: &mut L3G4200D<DI>, accelerometer: &mut A, timestamp: f64) -> Result<(f32, f32), FusionError<DI::Error, A::Error>> {
+        let sample = read_inputs(gyro, accelerometer)?;
+        self.update_at(sample.rate_dps, sample.acceleration, timestamp);
+        Ok(self.angles_deg())
+    }
+
+    /// Pitch and roll in radians.
+    pub fn angles_rad(&self) -> (f32, f32) {
+        self.angles.unwrap_or((0.0, 0.0))
+    }
+
+    /// Pitch and roll in degrees.
+    pub fn angles_deg(&self) -> (f32, f32) {
+        let (pitch, roll) = self.angles_rad();
+        (pitch.to_degrees(), roll.to_degrees())
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.time_constant);
+    }
+}
+
+// Roll goes all the way around, so blend across ±π the short way
+fn wrap(angle: f32) -> f32 {
+    atan2f(sinf(angle), cosf(angle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fusion::SyntheticAccelerometer;
+
+    fn accelerometer(pitch_deg: f32, roll_deg: f32) -> [f32; 3] {
+        let mut accelerometer = SyntheticAccelerometer::new();
+        accelerometer.set_tilt(pitch_deg.to_radians(), roll_deg.to_radians());
+        accelerometer.read_acceleration().unwrap()
+    }
+
+    #[test]
+    fn tilt_round_trip() {
+        let (pitch, roll) = tilt_from_acceleration(accelerometer(30.0, -45.0)).unwrap();
+        assert!((pitch.to_degrees() - 30.0).abs() < 1e-4 && (roll.to_degrees() + 45.0).abs() < 1e-4);
+        assert!(tilt_from_acceleration([0.0; 3]).is_none());
+    }
+
+    #[test]
+    fn starts_from_accelerometer() {
+        let mut filter = ComplementaryFilter::new(1.0);
+        filter.update_at((50.0, 50.0, 50.0), accelerometer(10.0, 20.0), 5.0);
+        let (pitch, roll) = filter.angles_deg();
+        assert!((pitch - 10.0).abs() < 1e-4 && (roll - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gyro_bias_stays_bounded() {
+        // Level and still, with 1 dps of uncompensated bias on X: integration alone would be off
+        // by 60° after a minute
+        let mut filter = ComplementaryFilter::new(0.5);
+        for step in 0..6000 {
+            filter.update_at((1.0, 0.0, 0.0), accelerometer(0.0, 0.0), step as f64 * 0.01);
+        }
+        let (pitch, roll) = filter.angles_deg();
+        assert!(pitch.abs() < 1e-3 && (roll - 0.5).abs() < 0.01, "{:?}", (pitch, roll));
+    }
+
+    #[test]
+    fn follows_gyro_through_short_disturbances() {
+        // Rolling at 90 dps for one second while the accelerometer is shaken
+        let mut filter = ComplementaryFilter::new(2.0);
+        filter.update((0.0, 0.0, 0.0), accelerometer(0.0, 0.0), 0.0);
+        for step in 1..=100 {
+            let roll = 0.9 * step as f32;
+            let mut acceleration = accelerometer(0.0, roll);
+            acceleration[1] += if step % 2 == 0 { 0.5 } else { -0.5 };
+            filter.update((90.0, 0.0, 0.0), acceleration, 0.01);
+        }
+        let (pitch, roll) = filter.angles_deg();
+        assert!(pitch.abs() < 0.5 && (roll - 90.0).abs() < 2.5, "{:?}", (pitch, roll));
+    }
+
+    #[test]
+    fn roll_wraps_around() {
+        let mut filter = ComplementaryFilter::new(0.1);
+        filter.update((0.0, 0.0, 0.0), accelerometer(0.0, 179.0), 0.0);
+        for _ in 0..200 {
+            filter.update((0.0, 0.0, 0.0), accelerometer(0.0, -179.0), 0.01);
+        }
+        assert!((filter.angles_rad().1 - (-179f32).to_radians()).abs() < 1e-3, "{:?}", filter.angles_deg());
+    }
+
+    #[test]
+    fn rolls_through_180_degrees() {
+        // A full turn about X at 90 dps, with the accelerometer following
+        let mut filter = ComplementaryFilter::new(0.5);
+        filter.update((0.0, 0.0, 0.0), accelerometer(0.0, 0.0), 0.0);
+        for step in 1..=400 {
+            let roll = 90.0 * step as f32 * 0.01;
+            filter.update((90.0, 0.0, 0.0), accelerometer(0.0, roll), 0.01);
+            let estimate = filter.angles_rad().1;
+            assert!(estimate.abs() <= core::f32::consts::PI, "{} at step {}", estimate.to_degrees(), step);
+            assert!(wrap(estimate - roll.to_radians()).abs() < 1e-3, "{} instead of {} at step {}", estimate.to_degrees(), roll, step);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use libm::{atan2, cos, sin, sqrt};
+
+use crate::errors::L3G4200DError;
+use crate::gyro::L3G4200D;
+use crate::interface::Interface;
+
+use super::complementary::tilt_from_acceleration;
+
+/// Gyroscope noise the filter is tuned for, e.g. from an Allan deviation of the sensor at rest.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct NoiseParameters {
+    /// White rate noise (angle random walk), in dps/√Hz. The datasheet gives 0.03 typical
+    pub noise_density_dps_sqrt_hz: f64,
+    /// Bias instability, in dps
+    pub bias_instability_dps: f64,
+    /// Time over which the bias wanders by about `bias_instability_dps`, in seconds. The bias is
+    /// modelled as a random walk reaching that spread after this long
+    pub bias_correlation_time_s: f64,
+}
+
+impl NoiseParameters {
+    /// Growth of the angle variance from white rate noise, in deg²/s.
+    pub fn angle_variance_rate(&self) -> f64 {
+        self.noise_density_dps_sqrt_hz * self.noise_density_dps_sqrt_hz
+    }
+
+    /// Growth of the bias variance, in (dps)²/s.
+    pub fn bias_variance_rate(&self) -> f64 {
+        self.bias_instability_dps * self.bias_instability_dps / self.bias_correlation_time_s
+    }
+}
+
+impl Default for NoiseParameters {
+    fn default() -> Self {
+        NoiseParameters {
+            noise_density_dps_sqrt_hz: 0.03,
+            bias_instability_dps: 0.02,
+            bias_correlation_time_s: 100.0,
+        }
+    }
+}
+
+// Difference between two angles in degrees, in -180..=180
+fn wrap_deg(angle: f64) -> f64 {
+    let radians = angle.to_radians();
+    atan2(sin(radians), cos(radians)).to_degrees()
+}
+
+/// Kalman filter over the angle about one axis (in degrees) and the bias left on its rate (in
+/// dps). The rate drives the prediction, and absolute angle measurements correct both states.
+///
+/// The angle starts at zero and exactly known, and the bias at zero with a standard deviation of
+/// `INITIAL_BIAS_STD_DEV_DPS`. Without measurements the bias cannot be observed, and the angle
+/// variance only grows.
+#[derive(Debug, Clone)]
+pub struct AxisKalman {
+    noise: NoiseParameters,
+    angle: f64,
+    bias: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl AxisKalman {
+    pub const INITIAL_BIAS_STD_DEV_DPS: f64 = 1.0;
+
+    pub fn new(noise: NoiseParameters) -> Self {
+        let bias_variance = Self::INITIAL_BIAS_STD_DEV_DPS * Self::INITIAL_BIAS_STD_DEV_DPS;
+        AxisKalman { noise, angle: 0.0, bias: 0.0, covariance: [[0.0, 0.0], [0.0, bias_variance]] }
+    }
+
+    pub fn noise(&self) -> &NoiseParameters {
+        &self.noise
+    }
+
+    /// Changes the process noise used by the next predictions.
+    pub fn set_noise(&mut self, noise: NoiseParameters) {
+        self.noise = noise;
+    }
+
+    /// Applies a rate sample over `dt` seconds. Non-positive intervals are ignored.
+    pub fn predict(&mut self, rate_dps: f64, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.angle += (rate_dps - self.bias) * dt;
+
+        // P = F P Fᵀ + Q, with F = [[1, -dt], [0, 1]] and Q integrated over the interval
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q_angle = self.noise.angle_variance_rate();
+        let q_bias = self.noise.bias_variance_rate();
+        let cross = p01 - dt * p11 - q_bias * dt * dt / 2.0;
+        self.covariance = [
+            [p00 - dt * (p01 + p10) + dt * dt * p11 + q_angle * dt + q_bias * dt * dt * dt / 3.0, cross],
+            [p10 - dt * p11 - q_bias * dt * dt / 2.0, p11 + q_bias * dt],
+        ];
+    }
+
+    /// Corrects the state with an absolute angle in degrees, measured with a standard deviation
+    /// of `std_dev_deg`. The difference to the estimate is wrapped to ±180°, so a heading in
+    /// 0..360 can correct an angle that went around several times. Returns that difference.
+    pub fn correct(&mut self, angle_deg: f64, std_dev_deg: f64) -> f64 {
+        let innovation = wrap_deg(angle_deg - self.angle);
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let variance = p00 + std_dev_deg * std_dev_deg;
+        if variance <= 0.0 {
+            return innovation;
+        }
+        let (k0, k1) = (p00 / variance, p10 / variance);
+        self.angle += k0 * innovation;
+        self.bias += k1 * innovation;
+        self.covariance = [[(1.0 - k0) * p00, (1.0 - k0) * p01], [p10 - k1 * p00, p11 - k1 * p01]];
+        innovation
+    }
+
+    pub fn angle_deg(&self) -> f64 {
+        self.angle
+    }
+
+    /// Bias estimated on the rate, in dps. It is already removed from the angle.
+    pub fn bias_dps(&self) -> f64 {
+        self.bias
+    }
+
+    /// Covariance of (angle, bias), in deg², deg·dps and (dps)².
+    pub fn covariance(&self) -> [[f64; 2]; 2] {
+        self.covariance
+    }
+
+    pub fn angle_std_dev_deg(&self) -> f64 {
+        sqrt(self.covariance[0][0].max(0.0))
+    }
+
+    pub fn bias_std_dev_dps(&self) -> f64 {
+        sqrt(self.covariance[1][1].max(0.0))
+    }
+
+    /// Replaces the angle, e.g. with a known starting heading, keeping the bias.
+    pub fn set_angle(&mut self, angle_deg: f64, std_dev_deg: f64) {
+        self.angle = angle_deg;
+        self.covariance[0] = [std_dev_deg * std_dev_deg, 0.0];
+        self.covariance[1][0] = 0.0;
+    }
+
+    /// Replaces the bias, e.g. with one estimated in a previous run, keeping the angle.
+    pub fn set_bias(&mut self, bias_dps: f64, std_dev_dps: f64) {
+        self.bias = bias_dps;
+        self.covariance[1] = [0.0, std_dev_dps * std_dev_dps];
+        self.covariance[0][1] = 0.0;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.noise);
+    }
+}
+
+/// One `AxisKalman` per axis, driven by the driver's rates in dps. Like the per-axis angles of
+/// `read_position`, the angles are plain integrals of each rate, so they only match roll, pitch
+/// and heading while rotations stay about one axis or the tilt stays small.
+#[derive(Debug, Clone)]
+pub struct KalmanFilter {
+    axes: [AxisKalman; 3],
+    previous_timestamp: Option<f64>,
+}
+
+impl KalmanFilter {
+    pub fn new(noise: NoiseParameters) -> Self {
+        KalmanFilter { axes: [0, 1, 2].map(|_| AxisKalman::new(noise)), previous_timestamp: None }
+    }
+
+    /// Filters of the X, Y and Z axes.
+    pub fn axes(&self) -> &[AxisKalman; 3] {
+        &self.axes
+    }
+
+    /// Gives access to the corrections and settings of each axis.
+    pub fn axes_mut(&mut self) -> &mut [AxisKalman; 3] {
+        &mut self.axes
+    }
+
+    /// Applies a rate sample over `dt` seconds.
+    pub fn predict(&mut self, rate_dps: (f32, f32, f32), dt: f32) {
+        for (axis, rate) in self.axes.iter_mut().zip([rate_dps.0, rate_dps.1, rate_dps.2]) {
+            axis.predict(rate as f64, dt as f64);
+        }
+    }
+
+    /// Applies a rate sample taken at `timestamp` seconds. The first call only starts the filter,
+    /// and timestamps that do not move forward are ignored.
+    pub fn predict_at(&mut self, rate_dps: (f32, f32, f32), timestamp: f64) {
+        match self.previous_timestamp {
+            Some(previous) if timestamp <= previous => return,
+            Some(previous) => self.predict(rate_dps, (timestamp - previous) as f32),
+            None => {}
+        }
+        self.previous_timestamp = Some(timestamp);
+    }
+
+    /// Reads the rate from `gyro`, taken at `timestamp` seconds, and returns the angles.
+    pub fn update_from<DI: Interface>(&mut self, gyro: &mut L3G4200D<DI>, timestamp: f64) -> Result<(f64, f64, f64), L3G4200DError<DI::Error>> {
+        let rate = gyro.read_rate_dps()?;
+        self.predict_at(rate, timestamp);
+        Ok(self.angles_deg())
+    }
+
+    /// Corrects the X (roll) and Y (pitch) angles with the tilt given by an accelerometer, with a
+    /// standard deviation of `std_dev_deg`. Returns false, changing nothing, for a zero vector.
+    pub fn correct_tilt(&mut self, acceleration: [f32; 3], std_dev_deg: f64) -> bool {
+        let Some((pitch, roll)) = tilt_from_acceleration(acceleration) else {
+            return false;
+        };
+        self.axes[0].correct((roll as f64).to_degrees(), std_dev_deg);
+        self.axes[1].correct((pitch as f64).to_degrees(), std_dev_deg);
+        true
+    }
+
+    /// Corrects the Z angle with an external heading in degrees, e.g. from a compass, with a
+    /// standard deviation of `std_dev_deg`.
+    pub fn correct_heading(&mut self, heading_deg: f64, std_dev_deg: f64) -> f64 {
+        self.axes[2].correct(heading_deg, std_dev_deg)
+    }
+
+    pub fn angles_deg(&self) -> (f64, f64, f64) {
+        let [x, y, z] = self.axes.each_ref().map(AxisKalman::angle_deg);
+        (x, y, z)
+    }
+
+    pub fn bias_dps(&self) -> (f64, f64, f64) {
+        let [x, y, z] = self.axes.each_ref().map(AxisKalman::bias_dps);
+        (x, y, z)
+    }
+
+    /// Standard deviation of each angle, e.g. to tell how far the heading can be trusted.
+    pub fn angle_std_dev_deg(&self) -> (f64, f64, f64) {
+        let [x, y, z] = self.axes.each_ref().map(AxisKalman::angle_std_dev_deg);
+        (x, y, z)
+    }
+
+    /// Forgets every estimate and timestamp, keeping the noise parameters.
+    pub fn reset(&mut self) {
+        for axis in self.axes.iter_mut() {
+            axis.reset();
+        }
+        self.previous_timestamp = None;
+    }
+}
+
+impl Default for KalmanFilter {
+    fn default() -> Self {
+        Self::new(NoiseParameters::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fusion::{Accelerometer, SyntheticAccelerometer};
+
+    // Uniform noise in -amplitude..amplitude, repeatable
+    struct Noise(u32);
+
+    impl Noise {
+        fn next(&mut self, amplitude: f64) -> f64 {
+            self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (self.0 as f64 / u32::MAX as f64 * 2.0 - 1.0) * amplitude
+        }
+    }
+
+    #[test]
+    fn variance_grows_without_measurements() {
+        let noise = NoiseParameters::default();
+        let mut filter = AxisKalman::new(noise);
+        filter.predict(10.0, 0.5);
+        filter.predict(10.0, 0.0);
+        assert_eq!(filter.angle_deg(), 5.0);
+
+        // Initial bias uncertainty, plus white noise, plus the bias random walk
+        let q = noise.bias_variance_rate();
+        let expected = 0.25 + noise.angle_variance_rate() * 0.5 + q * 0.125 / 3.0;
+        assert!((filter.covariance()[0][0] - expected).abs() < 1e-12);
+        assert!((filter.covariance()[1][1] - 1.0 - q * 0.5).abs() < 1e-12);
+        assert_eq!(filter.covariance()[0][1], filter.covariance()[1][0]);
+    }
+
+    #[test]
+    fn measurements_reveal_the_bias() {
+        let mut filter = AxisKalman::new(NoiseParameters::default());
+        let mut noise = Noise(7);
+        // Held at 30°, with 0.4 dps of bias and ±0.3 dps of noise, 100 Hz for a minute
+        filter.set_angle(30.0, 5.0);
+        for step in 0..6000 {
+            filter.predict(0.4 + noise.next(0.3), 0.01);
+            if step % 10 == 0 {
+                filter.correct(30.0 + noise.next(2.0), 2.0);
+            }
+        }
+        assert!((filter.bias_dps() - 0.4).abs() < 0.02, "{}", filter.bias_dps());
+        assert!((filter.angle_deg() - 30.0).abs() < 0.5, "{}", filter.angle_deg());
+        assert!(filter.bias_std_dev_dps() < 0.05 && filter.angle_std_dev_deg() < 1.0);
+
+        filter.reset();
+        assert_eq!((filter.angle_deg(), filter.bias_dps()), (0.0, 0.0));
+        assert_eq!(filter.bias_std_dev_dps(), AxisKalman::INITIAL_BIAS_STD_DEV_DPS);
+    }
+
+    #[test]
+    fn heading_wraps_around() {
+        let mut filter = AxisKalman::new(NoiseParameters::default());
+        filter.set_angle(710.0, 10.0);
+        assert!((filter.correct(10.0, 10.0) - 20.0).abs() < 1e-9);
+        assert!((filter.angle_deg() - 720.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tilt_corrects_roll_and_pitch() {
+        let mut filter = KalmanFilter::default();
+        let mut accelerometer = SyntheticAccelerometer::new();
+        accelerometer.set_tilt(0.2, -0.1);
+        // The tilt is unknown at first
+        for axis in &mut filter.axes_mut()[..2] {
+            axis.set_angle(0.0, 45.0);
+        }
+        for step in 0..500 {
+            filter.predict_at((0.0, 0.0, 0.0), step as f64 * 0.01);
+            assert!(filter.correct_tilt(accelerometer.read_acceleration().unwrap(), 1.0));
+        }
+        let (x, y, z) = filter.angles_deg();
+        assert!((x + 0.1f64.to_degrees()).abs() < 0.05 && (y - 0.2f64.to_degrees()).abs() < 0.05 && z == 0.0, "{:?}", (x, y, z));
+        assert!(!filter.correct_tilt([0.0; 3], 1.0));
+
+        // The heading is never measured, so it only grows less certain
+        let (sx, _, sz) = filter.angle_std_dev_deg();
+        assert!(sx < 0.1 && sz > 3.0, "{} {}", sx, sz);
+    }
+}
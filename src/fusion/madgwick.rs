@@ -0,0 +1,147 @@
+use libm::sqrt;
+
+use crate::attitude::Quaternion;
+use crate::gyro::L3G4200D;
+use crate::interface::Interface;
+
+use super::{ahrs_update_fns, normalize, orientation_from, read_inputs, Accelerometer, Ahrs, FusionError, ImuSample, Magnetometer};
+
+/// Gradient of ½|Rᵀd - s|² over the quaternion, for a reference direction `d = (dx, 0, dz)` and
+/// its measurement `s` in the body frame (Madgwick, 2010, eq. 25 and 26).
+fn gradient(q: &Quaternion, (dx, dz): (f64, f64), s: [f64; 3]) -> [f64; 4] {
+    let Quaternion { w, x, y, z } = *q;
+    let f = [
+        2.0 * dx * (0.5 - y * y - z * z) + 2.0 * dz * (x * z - w * y) - s[0],
+        2.0 * dx * (x * y - w * z) + 2.0 * dz * (w * x + y * z) - s[1],
+        2.0 * dx * (w * y + x * z) + 2.0 * dz * (0.5 - x * x - y * y) - s[2],
+    ];
+    let jacobian = [
+        [-2.0 * dz * y, 2.0 * dz * z, -4.0 * dx * y - 2.0 * dz * w, -4.0 * dx * z + 2.0 * dz * x],
+        [-2.0 * dx * z + 2.0 * dz * x, 2.0 * dx * y + 2.0 * dz * w, 2.0 * dx * x + 2.0 * dz * z, -2.0 * dx * w + 2.0 * dz * y],
+        [2.0 * dx * y, 2.0 * dx * z - 4.0 * dz * x, 2.0 * dx * w - 4.0 * dz * y, 2.0 * dx * x],
+    ];
+    [0, 1, 2, 3].map(|i| (0..3).map(|row| jacobian[row][i] * f[row]).sum())
+}
+
+/// Madgwick's gradient descent filter. Each update integrates the rate, then takes a step of
+/// `beta` (rad/s) against the gradient of the error between the measured and expected directions
+/// of gravity and, when given, of the magnetic field.
+///
+/// Higher `beta` follows the accelerometer and magnetometer more closely and corrects gyroscope
+/// errors faster, at the cost of more noise. Madgwick suggests √(3/4) times the gyroscope
+/// measurement error, in rad/s.
+#[derive(Debug, Clone)]
+pub struct Madgwick {
+    beta: f64,
+    orientation: Quaternion,
+    initialized: bool,
+    previous_timestamp: Option<f64>,
+}
+
+impl Madgwick {
+    pub const DEFAULT_BETA: f64 = 0.1;
+
+    pub fn new(beta: f64) -> Self {
+        Madgwick { beta, orientation: Quaternion::IDENTITY, initialized: false, previous_timestamp: None }
+    }
+
+    pub fn beta(&self) -> f64 {
+        self.beta
+    }
+
+    pub fn set_beta(&mut self, beta: f64) {
+        self.beta = beta;
+    }
+
+    ahrs_update_fns!();
+}
+
+impl Default for Madgwick {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BETA)
+    }
+}
+
+impl Ahrs for Madgwick {
+    fn update(&mut self, sample: &ImuSample, magnetic_field: Option<[f32; 3]>, dt: f32) {
+        if !self.initialized {
+            if let Some(orientation) = orientation_from(sample.acceleration, magnetic_field) {
+                self.orientation = orientation;
+                self.initialized = true;
+            }
+            return;
+        }
+        if dt <= 0.0 {
+            return;
+        }
+        let q = self.orientation;
+        let (x, y, z) = sample.rate_dps;
+        let rate = Quaternion::new(0.0, (x as f64).to_radians(), (y as f64).to_radians(), (z as f64).to_radians());
+        let half = q * rate;
+        let mut derivative = Quaternion::new(half.w / 2.0, half.x / 2.0, half.y / 2.0, half.z / 2.0);
+
+        if let Some(gravity) = normalize(sample.acceleration) {
+            let mut step = gradient(&q, (0.0, 1.0), gravity);
+            if let Some(field) = magnetic_field.and_then(normalize) {
+                // Reference field: the measured one brought to the horizontal plane and north
+                let (hx, hy, hz) = q.rotate((field[0], field[1], field[2]));
+                let magnetic_step = gradient(&q, (sqrt(hx * hx + hy * hy), hz), field);
+                step = [0, 1, 2, 3].map(|i| step[i] + magnetic_step[i]);
+            }
+            let norm = sqrt(step.iter().map(|value| value * value).sum());
+            if norm > 0.0 {
+                let [sw, sx, sy, sz] = step.map(|value| self.beta * value / norm);
+                derivative = Quaternion::new(derivative.w - sw, derivative.x - sx, derivative.y - sy, derivative.z - sz);
+            }
+        }
+
+        let dt = dt as f64;
+        self.orientation = Quaternion::new(q.w + derivative.w * dt, q.x + derivative.x * dt, q.y + derivative.y * dt, q.z + derivative.z * dt).normalize();
+    }
+
+    fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Quaternion) {
+        self.orientation = orientation.normalize();
+        self.initialized = true;
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.beta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attitude::EulerOrder;
+    use crate::fusion::tests::run_profile;
+
+    #[test]
+    fn gradient_vanishes_at_the_solution() {
+        let q = Quaternion::from_euler((0.4, -0.3, 1.1), EulerOrder::ZYX);
+        let (x, y, z) = q.conjugate().rotate((0.6, 0.0, -0.8));
+        for value in gradient(&q, (0.6, -0.8), [x, y, z]) {
+            assert!(value.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn tracks_rotation_with_heading() {
+        let mut filter = Madgwick::default();
+        let error = run_profile(&mut filter, [0.0; 3], true);
+        assert!(error < 1.0, "{} degrees off", error);
+    }
+
+    #[test]
+    fn magnetometer_stops_heading_drift() {
+        // With 2 dps of bias on Z, heading drifts without a magnetometer: gravity only corrects the tilt
+        let mut with_magnetometer = Madgwick::default();
+        let mut without = Madgwick::default();
+        let error = run_profile(&mut with_magnetometer, [0.0, 0.0, 2.0], true);
+        let drift = run_profile(&mut without, [0.0, 0.0, 2.0], false);
+        assert!(error < 2.0 && drift > 10.0, "{} vs {} degrees off", error, drift);
+    }
+}
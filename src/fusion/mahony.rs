@@ -0,0 +1,142 @@
+use crate::attitude::Quaternion;
+use crate::gyro::L3G4200D;
+use crate::interface::Interface;
+
+use super::{ahrs_update_fns, normalize, orientation_from, read_inputs, Accelerometer, Ahrs, FusionError, ImuSample, Magnetometer};
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Mahony's explicit complementary filter on the rotation group. The error between the measured
+/// and expected directions of gravity (and of the magnetic field, when given) is fed back into
+/// the rate, proportionally with `kp` and through an integral with `ki`.
+///
+/// The integral term converges to the opposite of the gyroscope bias, which `gyro_bias_dps`
+/// returns. With `ki` at zero, as in Mahony's reference implementation, no bias is estimated.
+#[derive(Debug, Clone)]
+pub struct Mahony {
+    kp: f64,
+    ki: f64,
+    // Rad/s, added to every rate
+    integral: [f64; 3],
+    orientation: Quaternion,
+    initialized: bool,
+    previous_timestamp: Option<f64>,
+}
+
+impl Mahony {
+    pub const DEFAULT_KP: f64 = 1.0;
+    pub const DEFAULT_KI: f64 = 0.0;
+
+    pub fn new(kp: f64, ki: f64) -> Self {
+        Mahony { kp, ki, integral: [0.0; 3], orientation: Quaternion::IDENTITY, initialized: false, previous_timestamp: None }
+    }
+
+    pub fn gains(&self) -> (f64, f64) {
+        (self.kp, self.ki)
+    }
+
+    /// Changes both gains. A zero `ki` also clears the bias estimate.
+    pub fn set_gains(&mut self, kp: f64, ki: f64) {
+        self.kp = kp;
+        self.ki = ki;
+        if ki == 0.0 {
+            self.integral = [0.0; 3];
+        }
+    }
+
+    /// Gyroscope bias estimated by the integral term, in degrees per second.
+    pub fn gyro_bias_dps(&self) -> (f64, f64, f64) {
+        let [x, y, z] = self.integral.map(|value| -value.to_degrees());
+        (x, y, z)
+    }
+
+    ahrs_update_fns!();
+}
+
+impl Default for Mahony {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_KP, Self::DEFAULT_KI)
+    }
+}
+
+impl Ahrs for Mahony {
+    fn update(&mut self, sample: &ImuSample, magnetic_field: Option<[f32; 3]>, dt: f32) {
+        if !self.initialized {
+            if let Some(orientation) = orientation_from(sample.acceleration, magnetic_field) {
+                self.orientation = orientation;
+                self.initialized = true;
+            }
+            return;
+        }
+        if dt <= 0.0 {
+            return;
+        }
+        let q = self.orientation;
+        let dt = dt as f64;
+        let mut error = [0.0; 3];
+        if let Some(gravity) = normalize(sample.acceleration) {
+            let (vx, vy, vz) = q.conjugate().rotate((0.0, 0.0, 1.0));
+            error = cross(gravity, [vx, vy, vz]);
+            if let Some(field) = magnetic_field.and_then(normalize) {
+                // Reference field: the measured one brought to the horizontal plane and north
+                let (hx, hy, hz) = q.rotate((field[0], field[1], field[2]));
+                let (wx, wy, wz) = q.conjugate().rotate((libm::sqrt(hx * hx + hy * hy), 0.0, hz));
+                let magnetic_error = cross(field, [wx, wy, wz]);
+                error = [0, 1, 2].map(|i| error[i] + magnetic_error[i]);
+            }
+        }
+        if self.ki > 0.0 {
+            for (integral, error) in self.integral.iter_mut().zip(error) {
+                *integral += self.ki * error * dt;
+            }
+        }
+
+        let (x, y, z) = sample.rate_dps;
+        let measured = [x, y, z].map(|value| (value as f64).to_radians());
+        let rate = [0, 1, 2].map(|i| measured[i] + self.kp * error[i] + self.integral[i]);
+        let speed = libm::sqrt(rate.iter().map(|value| value * value).sum());
+        let step = Quaternion::from_axis_angle((rate[0], rate[1], rate[2]), speed * dt);
+        self.orientation = (q * step).normalize();
+    }
+
+    fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Quaternion) {
+        self.orientation = orientation.normalize();
+        self.initialized = true;
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.kp, self.ki);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fusion::tests::run_profile;
+
+    #[test]
+    fn tracks_rotation_with_heading() {
+        let mut filter = Mahony::default();
+        let error = run_profile(&mut filter, [0.0; 3], true);
+        assert!(error < 1.0, "{} degrees off", error);
+    }
+
+    #[test]
+    fn integral_term_estimates_bias() {
+        let bias = [0.8, -0.5, 0.3];
+        let mut filter = Mahony::new(1.0, 0.1);
+        let error = run_profile(&mut filter, bias, true);
+        assert!(error < 1.0, "{} degrees off", error);
+        let (x, y, z) = filter.gyro_bias_dps();
+        assert!((x - 0.8).abs() < 0.05 && (y + 0.5).abs() < 0.05 && (z - 0.3).abs() < 0.05, "{:?}", (x, y, z));
+
+        filter.set_gains(1.0, 0.0);
+        assert_eq!(filter.gyro_bias_dps(), (0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,281 @@
+use core::convert::Infallible;
+
+use libm::{atan2, cos, cosf, sin, sinf, sqrt};
+use thiserror::Error;
+
+use crate::attitude::{EulerOrder, Quaternion};
+use crate::errors::L3G4200DError;
+use crate::gyro::L3G4200D;
+use crate::interface::Interface;
+
+pub mod complementary;
+pub mod kalman;
+pub mod madgwick;
+pub mod mahony;
+
+/// Source of acceleration in the frame of the gyroscope, e.g. an accelerometer driver mounted
+/// with the same axes. Only the direction of gravity is used, so the unit does not matter.
+pub trait Accelerometer {
+    type Error;
+
+    fn read_acceleration(&mut self) -> Result<[f32; 3], Self::Error>;
+}
+
+/// Accelerometer returning the gravity vector of a chosen tilt, for tests and simulations.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SyntheticAccelerometer {
+    acceleration: [f32; 3],
+}
+
+impl SyntheticAccelerometer {
+    /// Level, with 1 g pointing up along Z.
+    pub fn new() -> Self {
+        SyntheticAccelerometer { acceleration: [0.0, 0.0, 1.0] }
+    }
+
+    /// Gravity, in g, as seen by a device rotated by `pitch` and `roll` radians (Z-Y-X order).
+    pub fn set_tilt(&mut self, pitch: f32, roll: f32) {
+        self.acceleration = [-sinf(pitch), cosf(pitch) * sinf(roll), cosf(pitch) * cosf(roll)];
+    }
+
+    /// Gravity as seen by a device in `orientation` (body to reference frame, Z up).
+    pub fn set_orientation(&mut self, orientation: &Quaternion) {
+        let (x, y, z) = orientation.conjugate().rotate((0.0, 0.0, 1.0));
+        self.acceleration = [x as f32, y as f32, z as f32];
+    }
+
+    /// Overrides the reading, e.g. to add vibration or linear acceleration.
+    pub fn set_acceleration(&mut self, acceleration: [f32; 3]) {
+        self.acceleration = acceleration;
+    }
+}
+
+impl Default for SyntheticAccelerometer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accelerometer for SyntheticAccelerometer {
+    type Error = Infallible;
+
+    fn read_acceleration(&mut self) -> Result<[f32; 3], Self::Error> {
+        Ok(self.acceleration)
+    }
+}
+
+/// Source of the magnetic field in the frame of the gyroscope, e.g. a magnetometer driver mounted
+/// with the same axes and calibrated for hard and soft iron. Only the direction is used.
+pub trait Magnetometer {
+    type Error;
+
+    fn read_magnetic_field(&mut self) -> Result<[f32; 3], Self::Error>;
+}
+
+/// Magnetometer returning the Earth's field for a chosen orientation, for tests and simulations.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SyntheticMagnetometer {
+    inclination: f64,
+    field: [f32; 3],
+}
+
+impl SyntheticMagnetometer {
+    /// Field pointing north (along X of the reference frame) and dipping below the horizon by
+    /// `inclination` radians, seen by a device in the reference orientation.
+    pub fn new(inclination: f64) -> Self {
+        let mut magnetometer = SyntheticMagnetometer { inclination, field: [0.0; 3] };
+        magnetometer.set_orientation(&Quaternion::IDENTITY);
+        magnetometer
+    }
+
+    /// Field as seen by a device in `orientation` (body to reference frame, Z up).
+    pub fn set_orientation(&mut self, orientation: &Quaternion) {
+        let north = (cos(self.inclination), 0.0, -sin(self.inclination));
+        let (x, y, z) = orientation.conjugate().rotate(north);
+        self.field = [x as f32, y as f32, z as f32];
+    }
+}
+
+impl Magnetometer for SyntheticMagnetometer {
+    type Error = Infallible;
+
+    fn read_magnetic_field(&mut self) -> Result<[f32; 3], Self::Error> {
+        Ok(self.field)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FusionError<G, A, M = Infallible> {
+    #[error("Gyroscope error")]
+    Gyro(L3G4200DError<G>),
+    #[error("Accelerometer error")]
+    Accelerometer(A),
+    #[error("Magnetometer error")]
+    Magnetometer(M),
+}
+
+impl<G, A> FusionError<G, A> {
+    // Same error, for callers that also read a magnetometer
+    pub(crate) fn with_magnetometer<M>(self) -> FusionError<G, A, M> {
+        match self {
+            FusionError::Gyro(error) => FusionError::Gyro(error),
+            FusionError::Accelerometer(error) => FusionError::Accelerometer(error),
+            FusionError::Magnetometer(never) => match never {},
+        }
+    }
+}
+
+/// One input for the fusion filters.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ImuSample {
+    pub rate_dps: (f32, f32, f32),
+    pub acceleration: [f32; 3],
+}
+
+/// Reads the rate from `gyro`, with all of its compensation applied, and the acceleration from
+/// `accelerometer`.
+pub fn read_inputs<DI: Interface, A: Accelerometer>(gyro: &mut L3G4200D<DI>, accelerometer: &mut A) -> Result<ImuSample, FusionError<DI::Error, A::Error>> {
+    let rate_dps = gyro.read_rate_dps().map_err(FusionError::Gyro)?;
+    let acceleration = accelerometer.read_acceleration().map_err(FusionError::Accelerometer)?;
+    Ok(ImuSample { rate_dps, acceleration })
+}
+
+pub(crate) fn normalize(vector: [f32; 3]) -> Option<[f64; 3]> {
+    let [x, y, z] = vector.map(|value| value as f64);
+    let norm = sqrt(x * x + y * y + z * z);
+    if norm == 0.0 || !norm.is_finite() {
+        return None;
+    }
+    Some([x / norm, y / norm, z / norm])
+}
+
+/// Orientation (body to reference frame, Z up, X north) of a device at rest, from gravity and,
+/// when given, the magnetic field. Without a magnetometer the heading is zero. `None` if the
+/// acceleration is zero.
+pub fn orientation_from(acceleration: [f32; 3], magnetic_field: Option<[f32; 3]>) -> Option<Quaternion> {
+    let [x, y, z] = normalize(acceleration)?;
+    let tilt = Quaternion::from_euler((0.0, atan2(-x, sqrt(y * y + z * z)), atan2(y, z)), EulerOrder::ZYX);
+    let heading = match magnetic_field.and_then(normalize) {
+        Some([mx, my, mz]) => {
+            let (hx, hy, _) = tilt.rotate((mx, my, mz));
+            -atan2(hy, hx)
+        }
+        None => 0.0,
+    };
+    Some(Quaternion::from_axis_angle((0.0, 0.0, 1.0), heading) * tilt)
+}
+
+/// Attitude and heading reference: an orientation estimate fed with rates, acceleration and
+/// optionally the magnetic field. The orientation maps the body frame into a reference frame with
+/// Z up and, when a magnetometer is used, X pointing to magnetic north.
+pub trait Ahrs {
+    /// Applies `sample` over `dt` seconds. The first update starts from the orientation given by
+    /// the accelerometer (and magnetometer).
+    fn update(&mut self, sample: &ImuSample, magnetic_field: Option<[f32; 3]>, dt: f32);
+
+    fn orientation(&self) -> Quaternion;
+
+    fn set_orientation(&mut self, orientation: Quaternion);
+
+    /// Goes back to waiting for the first update.
+    fn reset(&mut self);
+
+    fn euler_rad(&self, order: EulerOrder) -> (f64, f64, f64) {
+        self.orientation().to_euler(order)
+    }
+
+    fn euler_deg(&self, order: EulerOrder) -> (f64, f64, f64) {
+        let (a, b, c) = self.euler_rad(order);
+        (a.to_degrees(), b.to_degrees(), c.to_degrees())
+    }
+}
+
+// Timestamped and driver-fed updates, identical for every `Ahrs`
+macro_rules! ahrs_update_fns {
+    () => {
+        /// Applies `sample` taken at `timestamp` seconds. The first call only starts the filter,
+        /// and timestamps that do not move forward are ignored.
+        pub fn update_at(&mut self, sample: &ImuSample, magnetic_field: Option<[f32; 3]>, timestamp: f64) {
+            match self.previous_timestamp {
+                Some(previous) if timestamp <= previous => return,
+                Some(previous) => self.update(sample, magnetic_field, (timestamp - previous) as f32),
+                None => self.update(sample, magnetic_field, 0.0),
+            }
+            self.previous_timestamp = Some(timestamp);
+        }
+
+        /// Reads `gyro` and `accelerometer`, taken at `timestamp` seconds, and updates the
+        /// estimate without a magnetometer.
+        pub fn update_from<DI: Interface, A: Accelerometer>(&mut self, gyro: &mut L3G4200D<DI>, accelerometer: &mut A, timestamp: f64) -> Result<Quaternion, FusionError<DI::Error, A::Error>> {
+            let sample = read_inputs(gyro, accelerometer)?;
+            self.update_at(&sample, None, timestamp);
+            Ok(self.orientation)
+        }
+
+        /// Reads `gyro`, `accelerometer` and `magnetometer`, taken at `timestamp` seconds, and
+        /// updates the estimate.
+        pub fn update_from_marg<DI: Interface, A: Accelerometer, M: Magnetometer>(&mut self, gyro: &mut L3G4200D<DI>, accelerometer: &mut A, magnetometer: &mut M, timestamp: f64) -> Result<Quaternion, FusionError<DI::Error, A::Error, M::Error>> {
+            let sample = read_inputs(gyro, accelerometer).map_err(FusionError::with_magnetometer::<M::Error>)?;
+            let magnetic_field = magnetometer.read_magnetic_field().map_err(FusionError::Magnetometer)?;
+            self.update_at(&sample, Some(magnetic_field), timestamp);
+            Ok(self.orientation)
+        }
+    };
+}
+pub(crate) use ahrs_update_fns;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::attitude::AttitudeEstimator;
+
+    pub(crate) fn angle_between(a: &Quaternion, b: &Quaternion) -> f64 {
+        let dot = (a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z).abs().min(1.0);
+        (2.0 * libm::acos(dot)).to_degrees()
+    }
+
+    // One minute at 100 Hz of a tilted device that turns about Z, then sways about X and
+    // Y, seen through a gyroscope with `bias_dps`. Returns the final error in degrees.
+    pub(crate) fn run_profile<F: Ahrs>(filter: &mut F, bias_dps: [f64; 3], with_magnetometer: bool) -> f64 {
+        let mut truth = AttitudeEstimator::new();
+        truth.set_orientation(Quaternion::from_euler((0.5, 0.2, 0.35), EulerOrder::ZYX));
+        let mut accelerometer = SyntheticAccelerometer::new();
+        let mut magnetometer = SyntheticMagnetometer::new(1.0);
+        let dt = 0.01;
+        for step in 0..6000 {
+            let t = step as f64 * dt;
+            let rate = if t < 20.0 {
+                (0.0, 0.0, 30.0)
+            } else {
+                (40.0 * libm::sin(t), 25.0 * libm::cos(0.7 * t), 0.0)
+            };
+            truth.update(rate, dt);
+            accelerometer.set_orientation(&truth.orientation());
+            magnetometer.set_orientation(&truth.orientation());
+            let sample = ImuSample {
+                rate_dps: ((rate.0 + bias_dps[0]) as f32, (rate.1 + bias_dps[1]) as f32, (rate.2 + bias_dps[2]) as f32),
+                acceleration: accelerometer.read_acceleration().unwrap(),
+            };
+            let field = magnetometer.read_magnetic_field().unwrap();
+            filter.update(&sample, with_magnetometer.then_some(field), if step == 0 { 0.0 } else { dt as f32 });
+        }
+        angle_between(&filter.orientation(), &truth.orientation())
+    }
+
+    #[test]
+    fn orientation_from_gravity_and_field() {
+        let truth = Quaternion::from_euler((-2.0, 0.4, -0.8), EulerOrder::ZYX);
+        let mut accelerometer = SyntheticAccelerometer::new();
+        let mut magnetometer = SyntheticMagnetometer::new(1.1);
+        accelerometer.set_orientation(&truth);
+        magnetometer.set_orientation(&truth);
+        let acceleration = accelerometer.read_acceleration().unwrap();
+        let estimate = orientation_from(acceleration, Some(magnetometer.read_magnetic_field().unwrap())).unwrap();
+        assert!(angle_between(&estimate, &truth) < 1e-3);
+
+        // Without a heading reference, only the tilt is known
+        let (_, pitch, roll) = orientation_from(acceleration, None).unwrap().to_euler(EulerOrder::ZYX);
+        assert!((pitch - 0.4).abs() < 1e-5 && (roll + 0.8).abs() < 1e-5);
+        assert!(orientation_from([0.0; 3], None).is_none());
+    }
+}
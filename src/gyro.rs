@@ -1,25 +1,62 @@
 
-use i2cdev::core::*;
-use i2cdev::linux::LinuxI2CDevice;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use embedded_hal::spi::SpiDevice;
+use libm::roundf;
 
+use crate::attitude::{AttitudeEstimator, Quaternion};
+use crate::calibration::{self, AxisAccumulator, Calibration, DeviceKey, DriftCalibrationOptions, DriftStatistics, TemperatureSample};
 use crate::config;
+use crate::config::ctrlreg1::DataRateBandwidth;
+use crate::config::ctrlreg4::{BigLittleEndian, BlockDataUpdate, FullScaleSelection, SelfTestEnabled, SerialInterfaceMode};
+use crate::config::fifo_src::FIFO_DEPTH;
+use crate::filter::Filter;
+use crate::integrator::{IntegrationMethod, Integrator};
+use crate::interface::{I2cInterface, Interface, SpiInterface};
 use crate::registers::L3G4200DRegister;
+use crate::self_test::{self, AxisSelfTest, SelfTestOptions, SelfTestReport};
+use crate::stationary::{DetectedPosition, MotionState, StationaryDetector};
+use crate::temperature::{Temperature, TemperatureChange};
 use crate::errors::L3G4200DError;
 
-const GYRO_K: i16 = 240; // 21845. / 90.;
-pub const L3G4200D_ADDR: u16 = 0x69;
+pub const L3G4200D_ADDR: u8 = 0x69; // SDO pulled high
+pub const L3G4200D_ADDR_SDO_LOW: u8 = 0x68;
 pub const L3G4200D_DEV_ID: u8 = 0xD3;
+pub(crate) const DATA_READY_POLL_US: u32 = 100;
+
+/// Everything from OUT_TEMP to OUT_Z_H, fetched in a single transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawSample {
+    pub temperature: u8,
+    pub status: config::status_reg::Value,
+    pub delta: (i16, i16, i16),
+}
 
-pub struct L3G4200D {
-    i2c: LinuxI2CDevice,
-    coords: [i16; 3],
-    drift_compensation: [i16; 3],
+pub struct L3G4200D<DI> {
+    iface: DI,
+    integrator: Integrator,
+    attitude: AttitudeEstimator,
+    // Digits added to every sample, kept fractional so that float outputs stay sub-digit accurate
+    drift_compensation: [f32; 3],
     temp_compensation: i8,
+    // Whether temp_compensation comes from a known temperature
+    temperature_referenced: bool,
+    // First OUT_TEMP reading, for the change since boot
+    boot_temperature: Option<i8>,
+    // Applied to bias-free rates in dps
+    correction: [[f32; 3]; 3],
+    temperature_reference: f32,
+    temperature_coefficients: [[f32; 2]; 3],
+    // Latest OUT_TEMP reading in digits, for the temperature model
+    temperature: Option<f32>,
+    full_scale: FullScaleSelection,
+    byte_order: BigLittleEndian,
+    data_rate: DataRateBandwidth,
 }
 
 macro_rules! write_reg_fn {
     ( $f:ident, $reg:expr, $cfg:ty ) => {
-        pub fn $f(&mut self, value: $cfg) -> Result<(), L3G4200DError> {
+        pub fn $f(&mut self, value: $cfg) -> Result<(), L3G4200DError<DI::Error>> {
             self.write_byte_data($reg, value.to_value())
         }
     };
@@ -27,90 +64,465 @@ macro_rules! write_reg_fn {
 
 macro_rules! read_reg_fn {
     ( $f:ident, $reg:expr, $cfg:ty ) => {
-        pub fn $f(&mut self) -> Result<$cfg, L3G4200DError> {
+        pub fn $f(&mut self) -> Result<$cfg, L3G4200DError<DI::Error>> {
             self.read_byte_data($reg).map(|raw| raw.into())
         }
     };
 }
 
-impl L3G4200D {
-    pub fn new(channel: u8) -> Self {
-        L3G4200D { 
-            i2c: LinuxI2CDevice::new(format!("/dev/i2c-{}", channel), L3G4200D_ADDR).expect("Failed to open I2C device"),
-            coords: [0, 0, 0],
-            drift_compensation: [0, 0, 0],
+pub(crate) fn to_u16(low: u8, high: u8) -> u16 {
+    (high as u16) << 8 | low as u16
+}
+
+// Rust integers are already two's complement, so reinterpreting the bits is enough
+// (negating the magnitude by hand overflows on i16::MIN)
+pub(crate) fn from_twos(value: u16) -> i16 {
+    value as i16
+}
+
+fn from_twos_u8(value: u8) -> i8 {
+    value as i8
+}
+
+// SIM must follow the wiring, otherwise a 3-wire bus stops answering
+pub(crate) fn with_sim(ctrl_reg4: u8, mode: Option<SerialInterfaceMode>) -> u8 {
+    match mode {
+        Some(mode) => config::ctrlreg4::Value { serial_interface_mode: mode, ..ctrl_reg4.into() }.to_value(),
+        None => ctrl_reg4,
+    }
+}
+
+// With BLE set, the sensor puts the high byte of each axis at the lower address
+pub(crate) fn decode_axes(data: &[u8], byte_order: BigLittleEndian) -> (i16, i16, i16) {
+    let axis = |offset: usize| {
+        let (first, second) = (data[offset], data[offset + 1]);
+        if byte_order == BigLittleEndian::BIG_ENDIAN {
+            from_twos(to_u16(second, first))
+        } else {
+            from_twos(to_u16(first, second))
+        }
+    };
+    (axis(0), axis(2), axis(4))
+}
+
+impl<I2C: I2c> L3G4200D<I2cInterface<I2C>> {
+    pub fn new_i2c(i2c: I2C) -> Self {
+        Self::with_interface(I2cInterface::new(i2c))
+    }
+
+    pub fn new_i2c_with_address(i2c: I2C, address: u8) -> Self {
+        Self::with_interface(I2cInterface::with_address(i2c, address))
+    }
+
+    /// Looks for the sensor on both addresses selectable through SDO, returning the first one whose
+    /// WHO_AM_I matches `L3G4200D_DEV_ID`. The bus is dropped if no sensor answers.
+    pub fn probe_i2c(mut i2c: I2C) -> Result<Self, L3G4200DError<I2C::Error>> {
+        for address in [L3G4200D_ADDR, L3G4200D_ADDR_SDO_LOW] {
+            let mut gyro = Self::new_i2c_with_address(i2c, address);
+            match gyro.who_am_i() {
+                Ok(L3G4200D_DEV_ID) => return Ok(gyro),
+                _ => i2c = gyro.release(),
+            }
+        }
+        Err(L3G4200DError::DeviceNotFound())
+    }
+
+    pub fn address(&self) -> u8 {
+        self.iface.address()
+    }
+
+    /// Applies `calibration` if it was made for the sensor at this address on `bus`, or for no
+    /// sensor in particular. Returns whether it was applied.
+    pub fn apply_calibration_for(&mut self, bus: u8, calibration: &Calibration) -> bool {
+        let matches = calibration.matches(bus, self.address());
+        if matches {
+            self.apply_calibration(calibration);
+        }
+        matches
+    }
+
+    /// Same as `calibration`, keyed with `bus` and the address of this sensor.
+    pub fn keyed_calibration(&self, bus: u8) -> Calibration {
+        Calibration {
+            device: Some(DeviceKey { bus, address: self.address() }),
+            ..self.calibration()
+        }
+    }
+
+    pub fn release(self) -> I2C {
+        self.iface.release()
+    }
+}
+
+impl<SPI: SpiDevice> L3G4200D<SpiInterface<SPI>> {
+    /// Also writes CTRL_REG4 so that SIM matches `mode`, which resets the other CTRL_REG4 fields.
+    pub fn new_spi(spi: SPI, mode: SerialInterfaceMode) -> Result<Self, L3G4200DError<SPI::Error>> {
+        let mut gyro = Self::with_interface(SpiInterface::new(spi, mode));
+        gyro.write_config_reg_4(Default::default())?;
+        Ok(gyro)
+    }
+
+    pub fn release(self) -> SPI {
+        self.iface.release()
+    }
+}
+
+impl<DI: Interface> L3G4200D<DI> {
+    pub fn with_interface(iface: DI) -> Self {
+        L3G4200D {
+            iface,
+            integrator: Integrator::default(),
+            attitude: AttitudeEstimator::new(),
+            drift_compensation: [0.0, 0.0, 0.0],
             temp_compensation: 0,
+            temperature_referenced: false,
+            boot_temperature: None,
+            correction: calibration::IDENTITY,
+            temperature_reference: 0.0,
+            temperature_coefficients: [[0.0; 2]; 3],
+            temperature: None,
+            full_scale: FullScaleSelection::FSS_250_DPS,
+            byte_order: BigLittleEndian::LITTLE_ENDIAN,
+            data_rate: DataRateBandwidth::ODR_100_CUT_OFF_12_5,
+        }
+    }
+
+    /// Writes one register. On a bus that needs a particular SIM setting, CTRL_REG4 is always
+    /// written with it, whatever `value` says.
+    pub fn write_byte_data(&mut self, reg: L3G4200DRegister, mut value: u8) -> Result<(), L3G4200DError<DI::Error>> {
+        reg.enforce_write_protect(value)?;
+        if reg.reg() == L3G4200DRegister::CTRL_REG4.reg() {
+            value = with_sim(value, self.iface.serial_interface_mode());
+        }
+        self.iface.write_register(reg.reg(), value)
+            .map_err(L3G4200DError::BusError)?;
+        // Keep decoding and unit conversions in step with CTRL_REG1 and CTRL_REG4, however they
+        // get written
+        if reg.reg() == L3G4200DRegister::CTRL_REG1.reg() {
+            self.data_rate = config::ctrlreg1::Value::from(value).dr_bw;
+        } else if reg.reg() == L3G4200DRegister::CTRL_REG4.reg() {
+            let ctrl_reg4 = config::ctrlreg4::Value::from(value);
+            self.full_scale = ctrl_reg4.full_scale_select;
+            self.byte_order = ctrl_reg4.big_little_endian;
+        }
+        Ok(())
+    }
+
+    pub fn read_byte_data(&mut self, reg: L3G4200DRegister) -> Result<u8, L3G4200DError<DI::Error>> {
+        self.iface.read_register(reg.reg())
+            .map_err(L3G4200DError::BusError)
+    }
+
+    /// Reads `buffer.len()` consecutive registers starting at `reg` in one transaction.
+    pub fn read_block_data(&mut self, reg: L3G4200DRegister, buffer: &mut [u8]) -> Result<(), L3G4200DError<DI::Error>> {
+        self.iface.read_registers(reg.reg(), buffer)
+            .map_err(L3G4200DError::BusError)
+    }
+
+    fn decode_delta(&self, data: &[u8]) -> (i16, i16, i16) {
+        let (x, y, z) = decode_axes(data, self.byte_order);
+        let [cx, cy, cz] = self.drift_compensation.map(|c| roundf(c) as i16);
+        (x.saturating_add(cx), y.saturating_add(cy), z.saturating_add(cz))
+    }
+
+    pub fn read_raw_temperature_delta(&mut self) -> Result<u8, L3G4200DError<DI::Error>> {
+        self.read_byte_data(L3G4200DRegister::OUT_TEMP)
+    }
+
+    /// OUT_TEMP (-1 digit/°C) plus the offset set by `callibrate_temperature` or
+    /// `set_temperature_reference`. Without either, this is only relative to an unknown point.
+    pub fn read_temperature(&mut self) -> Result<i8, L3G4200DError<DI::Error>> {
+        let raw = self.read_raw_temperature_delta()?;
+        self.track_temperature(raw);
+        Ok(self.temp_compensation.saturating_sub(from_twos_u8(raw)))
+    }
+
+    // OUT_TEMP goes down by one digit per °C
+    fn track_temperature(&mut self, raw: u8) {
+        self.boot_temperature.get_or_insert(from_twos_u8(raw));
+        self.temperature = Some(from_twos_u8(raw) as f32);
+    }
+
+    /// Uses the temperature given by an external thermometer, right now, as the reference for
+    /// later readings. OUT_TEMP has a resolution of 1 °C, so the reference is rounded to that.
+    pub fn set_temperature_reference(&mut self, temperature: Temperature) -> Result<(), L3G4200DError<DI::Error>> {
+        let raw = self.read_raw_temperature_delta()?;
+        let celsius = roundf(temperature.celsius()).clamp(i8::MIN as f32, i8::MAX as f32) as i8;
+        self.temp_compensation = celsius.saturating_add(from_twos_u8(raw));
+        self.temperature_referenced = true;
+        self.track_temperature(raw);
+        Ok(())
+    }
+
+    /// Temperature with the reference applied. Fails with `L3G4200DError::NoTemperatureReference`
+    /// until one is set, since OUT_TEMP alone has no absolute meaning.
+    pub fn read_absolute_temperature(&mut self) -> Result<Temperature, L3G4200DError<DI::Error>> {
+        if !self.temperature_referenced {
+            return Err(L3G4200DError::NoTemperatureReference());
         }
+        self.read_temperature().map(|celsius| Temperature::from_celsius(celsius as f32))
+    }
+
+    /// Change since the first OUT_TEMP reading made by this driver, which needs no reference.
+    /// Read the temperature once right after power-up for this to be the change since boot.
+    pub fn read_temperature_change(&mut self) -> Result<TemperatureChange, L3G4200DError<DI::Error>> {
+        let raw = self.read_raw_temperature_delta()?;
+        self.track_temperature(raw);
+        Ok(TemperatureChange::from_digits(self.boot_temperature.unwrap_or(from_twos_u8(raw)), from_twos_u8(raw)))
+    }
+
+    fn has_temperature_model(&self) -> bool {
+        self.temperature_coefficients != [[0.0; 2]; 3]
     }
 
-    pub fn new_safe(channel: u8) -> Result<Self, String> {
-        if let Ok(device) = LinuxI2CDevice::new(format!("/dev/i2c-{}", channel), L3G4200D_ADDR) {
-            Ok(L3G4200D { 
-                i2c: device,
-                coords: [0, 0, 0],
-                drift_compensation: [0, 0, 0],
-                temp_compensation: 0
-            })
+    // Reads OUT_TEMP along with the outputs while a temperature model is set, so that rates always
+    // use a recent temperature
+    fn read_delta_for_rate(&mut self) -> Result<(i16, i16, i16), L3G4200DError<DI::Error>> {
+        if self.has_temperature_model() {
+            self.read_raw_sample().map(|sample| sample.delta)
         } else {
-            Err("Failed to open I2C device".to_string())
+            self.read_raw_delta()
         }
     }
 
-    pub fn write_byte_data(&mut self, reg: L3G4200DRegister, value: u8) -> Result<(), L3G4200DError> {
-        reg.enforce_write_protect(value)?;
-        self.i2c.smbus_write_byte_data(reg.reg(), value)
-            .map_err(From::from)
+    pub fn read_raw_delta(&mut self) -> Result<(i16, i16, i16), L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 6];
+        self.read_block_data(L3G4200DRegister::OUT_X_L, &mut data)?;
+        Ok(self.decode_delta(&data))
     }
 
-    pub fn read_byte_data(&mut self, reg: L3G4200DRegister) -> Result<u8, L3G4200DError> {
-        self.i2c.smbus_read_byte_data(reg.reg())
-            .map_err(From::from)
+    pub fn read_raw_sample(&mut self) -> Result<RawSample, L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 8];
+        self.read_block_data(L3G4200DRegister::OUT_TEMP, &mut data)?;
+        self.track_temperature(data[0]);
+        Ok(RawSample {
+            temperature: data[0],
+            status: data[1].into(),
+            delta: self.decode_delta(&data[2..]),
+        })
     }
 
-    fn _to_u16(low: u8, high: u8) -> u16 {
-        (high as u16) << 8 | low as u16
+    /// Polls STATUS_REG until a new sample is available on all three axes, giving up with
+    /// `L3G4200DError::Timeout` after `timeout_us` microseconds.
+    pub fn wait_for_data_ready<D: DelayNs>(&mut self, delay: &mut D, timeout_us: u32) -> Result<config::status_reg::Value, L3G4200DError<DI::Error>> {
+        let mut waited_us = 0;
+        loop {
+            let status = self.read_status()?;
+            if status.zyx_available {
+                return Ok(status);
+            }
+            if waited_us >= timeout_us {
+                return Err(L3G4200DError::Timeout());
+            }
+            delay.delay_us(DATA_READY_POLL_US);
+            waited_us += DATA_READY_POLL_US;
+        }
     }
 
-    fn _from_twos(value: u16) -> i16 {
-        if value & (1 << 15) != 0 {-((!value +1) as i16)} else { value as i16 }
+    /// Waits for a new sample and reads it, so that no sample is ever returned twice. If
+    /// `status.zyx_overrun` is set on the result, at least one sample was lost since the previous
+    /// read.
+    pub fn read_sample_when_ready<D: DelayNs>(&mut self, delay: &mut D, timeout_us: u32) -> Result<RawSample, L3G4200DError<DI::Error>> {
+        self.wait_for_data_ready(delay, timeout_us)?;
+        self.read_raw_sample()
     }
 
-    fn _from_twos_u8(value: u8) -> i8 {
-        if value & (1 << 7) != 0 {-((!value +1) as i8)} else { value as i8 }
+    /// Full scale used to convert readings to physical units. It follows every write to CTRL_REG4
+    /// and starts at the power-on value, 250 dps.
+    pub fn full_scale(&self) -> FullScaleSelection {
+        self.full_scale
     }
 
-    pub fn read_raw_temperature_delta(&mut self) -> Result<u8, L3G4200DError> {
-        self.read_byte_data(L3G4200DRegister::OUT_TEMP)
+    /// Reads the full scale back from CTRL_REG4, for a sensor that was configured before the
+    /// driver was attached.
+    pub fn refresh_full_scale(&mut self) -> Result<FullScaleSelection, L3G4200DError<DI::Error>> {
+        self.full_scale = self.read_config_reg_4()?.full_scale_select;
+        Ok(self.full_scale)
     }
 
-    pub fn read_temperature(&mut self) -> Result<i8, L3G4200DError> {
-        self.read_raw_temperature_delta().map(Self::_from_twos_u8).map(|temp| -1 * temp + self.temp_compensation)
+    /// Byte order the output registers are decoded with. It follows every write to CTRL_REG4 and
+    /// starts at the power-on value, little endian.
+    pub fn byte_order(&self) -> BigLittleEndian {
+        self.byte_order
     }
 
-    pub fn read_raw_delta(&mut self) -> Result<(i16, i16, i16), L3G4200DError> {
-        let x_l = self.read_byte_data(L3G4200DRegister::OUT_X_L)?;
-        let x_h = self.read_byte_data(L3G4200DRegister::OUT_X_H)?;
-        let y_l = self.read_byte_data(L3G4200DRegister::OUT_Y_L)?;
-        let y_h = self.read_byte_data(L3G4200DRegister::OUT_Y_H)?;
-        let z_l = self.read_byte_data(L3G4200DRegister::OUT_Z_L)?;
-        let z_h = self.read_byte_data(L3G4200DRegister::OUT_Z_H)?;
+    /// Reads the byte order back from CTRL_REG4, for a sensor that was configured before the
+    /// driver was attached.
+    pub fn refresh_byte_order(&mut self) -> Result<BigLittleEndian, L3G4200DError<DI::Error>> {
+        self.byte_order = self.read_config_reg_4()?.big_little_endian;
+        Ok(self.byte_order)
+    }
 
-        let x = Self::_from_twos(Self::_to_u16(x_l, x_h)) + self.drift_compensation[0];
-        let y = Self::_from_twos(Self::_to_u16(y_l, y_h)) + self.drift_compensation[1];
-        let z = Self::_from_twos(Self::_to_u16(z_l, z_h)) + self.drift_compensation[2];
+    /// Output data rate used to space samples in time when integrating. It follows every write to
+    /// CTRL_REG1 and starts at the power-on value, 100 Hz.
+    pub fn data_rate(&self) -> DataRateBandwidth {
+        self.data_rate
+    }
 
-        Ok((x, y, z))
+    /// Reads the output data rate back from CTRL_REG1, for a sensor that was configured before
+    /// the driver was attached.
+    pub fn refresh_data_rate(&mut self) -> Result<DataRateBandwidth, L3G4200DError<DI::Error>> {
+        self.data_rate = self.read_config_reg_1()?.dr_bw;
+        Ok(self.data_rate)
     }
 
-    pub fn read_position(&mut self) -> Result<(i16, i16, i16), L3G4200DError> {
-        let (x, y, z) = self.read_raw_delta()?;
-        self.coords[0] += x / GYRO_K;
-        self.coords[1] += y / GYRO_K;
-        self.coords[2] += z / GYRO_K;
-        Ok((self.coords[0], self.coords[1], self.coords[2]))
+    /// Converts a delta returned by the driver (e.g. from `drain_fifo` or `read_raw_sample`) to
+    /// degrees per second, applying the temperature model (with the latest temperature read) and
+    /// the scale and misalignment correction.
+    pub fn to_dps(&self, delta: (i16, i16, i16)) -> (f32, f32, f32) {
+        self.apply_correction(self.to_uncorrected_dps(delta))
+    }
+
+    // Rate in dps with the drift compensation and temperature model applied, in the frame of the
+    // compensation, i.e. before the scale and misalignment correction
+    fn to_uncorrected_dps(&self, delta: (i16, i16, i16)) -> [f32; 3] {
+        // Deltas carry the drift compensation rounded to whole digits, add back the fraction
+        let sensitivity = self.full_scale.sensitivity_mdps() / 1000.0;
+        let axis = |value: i16, compensation: f32| (value as f32 + compensation - roundf(compensation)) * sensitivity;
+        let [cx, cy, cz] = self.drift_compensation;
+        let mut rate = [axis(delta.0, cx), axis(delta.1, cy), axis(delta.2, cz)];
+        if let Some(temperature) = self.temperature {
+            let drift = calibration::temperature_drift(&self.temperature_coefficients, self.temperature_reference, temperature);
+            for (value, drift) in rate.iter_mut().zip(drift) {
+                *value -= drift;
+            }
+        }
+        rate
+    }
+
+    fn apply_correction(&self, rate: [f32; 3]) -> (f32, f32, f32) {
+        let [x, y, z] = self.correction.map(|row| row[0] * rate[0] + row[1] * rate[1] + row[2] * rate[2]);
+        (x, y, z)
+    }
+
+    /// Replaces the drift and temperature compensation with `calibration`, whatever sensor it was
+    /// made for. The bias is converted to digits with the current full scale, so apply it after
+    /// CTRL_REG4 is set.
+    pub fn apply_calibration(&mut self, calibration: &Calibration) {
+        let digits_per_dps = 1000.0 / self.full_scale.sensitivity_mdps();
+        self.drift_compensation = calibration.bias_dps.map(|bias| -bias * digits_per_dps);
+        self.correction = calibration.correction;
+        self.temp_compensation = calibration.temperature_offset.unwrap_or(0);
+        self.temperature_referenced = calibration.temperature_offset.is_some();
+        self.temperature_reference = calibration.temperature_reference;
+        self.temperature_coefficients = calibration.temperature_coefficients;
+    }
+
+    /// Scale and misalignment correction applied by `to_dps`, row by row.
+    pub fn correction(&self) -> [[f32; 3]; 3] {
+        self.correction
+    }
+
+    /// Replaces the scale and misalignment correction, e.g. with the result of
+    /// `calibration::fit_correction`. Set it back to `calibration::IDENTITY` before recording
+    /// rotations for a new fit.
+    pub fn set_correction(&mut self, correction: [[f32; 3]; 3]) {
+        self.correction = correction;
+    }
+
+    /// Current compensation, not tied to any sensor. Save it and pass it to `apply_calibration`
+    /// on a later run to skip calibrating again.
+    pub fn calibration(&self) -> Calibration {
+        let dps_per_digit = self.full_scale.sensitivity_mdps() / 1000.0;
+        Calibration {
+            bias_dps: self.drift_compensation.map(|compensation| -compensation * dps_per_digit),
+            correction: self.correction,
+            temperature_offset: self.temperature_referenced.then_some(self.temp_compensation),
+            temperature_reference: self.temperature_reference,
+            temperature_coefficients: self.temperature_coefficients,
+            ..Default::default()
+        }
+    }
+
+    /// Angular rate in degrees per second.
+    pub fn read_rate_dps(&mut self) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        self.read_delta_for_rate().map(|delta| self.to_dps(delta))
+    }
+
+    /// Angular rate in radians per second.
+    pub fn read_rate_rad_s(&mut self) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        let (x, y, z) = self.read_rate_dps()?;
+        Ok((x.to_radians(), y.to_radians(), z.to_radians()))
+    }
+
+    pub fn set_integration_method(&mut self, method: IntegrationMethod) {
+        self.integrator.set_method(method);
+    }
+
+    /// Integrates deltas taken one output data period apart (e.g. drained from the FIFO) into
+    /// both the per-axis angles and the attitude, returning the accumulated angles in degrees.
+    pub fn integrate(&mut self, samples: &[(i16, i16, i16)]) -> (f64, f64, f64) {
+        for &delta in samples {
+            let rate = self.to_dps(delta);
+            self.integrate_rate(rate);
+        }
+        self.integrator.angles_deg()
+    }
+
+    // Applies a rate in dps over one output data period
+    fn integrate_rate(&mut self, (x, y, z): (f32, f32, f32)) {
+        let dt = 1.0 / self.data_rate.output_data_rate_hz() as f64;
+        self.integrator.update((x as f64, y as f64, z as f64), dt);
+        self.attitude.update((x as f64, y as f64, z as f64), dt);
+    }
+
+    /// Reads one sample and integrates it over one output data period, returning the accumulated
+    /// angles in degrees. Each call has to consume exactly one new sample for the result to be
+    /// right, so pace it with `wait_for_data_ready` or use `read_position_at`.
+    pub fn read_position(&mut self) -> Result<(f64, f64, f64), L3G4200DError<DI::Error>> {
+        let delta = self.read_delta_for_rate()?;
+        Ok(self.integrate(&[delta]))
     }
 
-    pub fn read_delta_filtered<F>(&mut self, filter: F) -> Result<(i16, i16, i16), L3G4200DError> where F: Fn(i16) -> i16 {
+    /// Reads one sample taken at `timestamp` seconds and integrates it since the previous
+    /// timestamped read, returning the accumulated angles in degrees.
+    pub fn read_position_at(&mut self, timestamp: f64) -> Result<(f64, f64, f64), L3G4200DError<DI::Error>> {
+        let (x, y, z) = self.read_rate_dps()?;
+        self.integrator.update_at((x as f64, y as f64, z as f64), timestamp);
+        self.attitude.update_at((x as f64, y as f64, z as f64), timestamp);
+        Ok(self.integrator.angles_deg())
+    }
+
+    /// Reads one sample and applies it over one output data period, returning the orientation.
+    /// Like `read_position`, each call has to consume exactly one new sample.
+    pub fn read_attitude(&mut self) -> Result<Quaternion, L3G4200DError<DI::Error>> {
+        let delta = self.read_delta_for_rate()?;
+        self.integrate(&[delta]);
+        Ok(self.attitude.orientation())
+    }
+
+    /// Reads one sample taken at `timestamp` seconds and applies it since the previous timestamped
+    /// read, returning the orientation.
+    pub fn read_attitude_at(&mut self, timestamp: f64) -> Result<Quaternion, L3G4200DError<DI::Error>> {
+        self.read_position_at(timestamp)?;
+        Ok(self.attitude.orientation())
+    }
+
+    /// Orientation tracked from every sample read through the position and attitude methods.
+    pub fn attitude(&self) -> &AttitudeEstimator {
+        &self.attitude
+    }
+
+    /// Gives access to `reset` and `set_orientation` on the tracked attitude.
+    pub fn attitude_mut(&mut self) -> &mut AttitudeEstimator {
+        &mut self.attitude
+    }
+
+    /// Accumulated angles in degrees.
+    pub fn position_deg(&self) -> (f64, f64, f64) {
+        self.integrator.angles_deg()
+    }
+
+    /// Accumulated angles in radians.
+    pub fn position_rad(&self) -> (f64, f64, f64) {
+        self.integrator.angles_rad()
+    }
+
+    pub fn reset_position(&mut self) {
+        self.integrator.reset();
+    }
+
+    pub fn read_delta_filtered<F>(&mut self, filter: F) -> Result<(i16, i16, i16), L3G4200DError<DI::Error>> where F: Fn(i16) -> i16 {
         let (mut x, mut y, mut z) = self.read_raw_delta()?;
 
         x = filter(x);
@@ -120,15 +532,77 @@ impl L3G4200D {
         Ok((x, y, z))
     }
 
-    pub fn read_position_filtered<F>(&mut self, filter: F) -> Result<(i16, i16, i16), L3G4200DError> where F: Fn(i16) -> i16 {
-        let (x, y, z) = self.read_delta_filtered(filter)?;
-        self.coords[0] += x / GYRO_K;
-        self.coords[1] += y / GYRO_K;
-        self.coords[2] += z / GYRO_K;
-        Ok((self.coords[0], self.coords[1], self.coords[2]))
+    pub fn read_position_filtered<F>(&mut self, filter: F) -> Result<(f64, f64, f64), L3G4200DError<DI::Error>> where F: Fn(i16) -> i16 {
+        let delta = self.read_delta_filtered(filter)?;
+        Ok(self.integrate(&[delta]))
+    }
+
+    /// Reads one delta and passes it through `filter`, which keeps its state between calls.
+    pub fn read_delta_with<F: Filter>(&mut self, filter: &mut F) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        let (x, y, z) = self.read_raw_delta()?;
+        let [x, y, z] = filter.apply([x as f32, y as f32, z as f32]);
+        Ok((x, y, z))
     }
 
-    pub fn callibrate_drift(&mut self, iterations: i32) -> Result<[i32; 3], L3G4200DError> {
+    /// Angular rate in degrees per second, passed through `filter`.
+    pub fn read_rate_dps_with<F: Filter>(&mut self, filter: &mut F) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        let (x, y, z) = self.read_rate_dps()?;
+        let [x, y, z] = filter.apply([x, y, z]);
+        Ok((x, y, z))
+    }
+
+    /// Like `read_position`, with the rate in degrees per second passed through `filter` before
+    /// it is integrated.
+    pub fn read_position_with<F: Filter>(&mut self, filter: &mut F) -> Result<(f64, f64, f64), L3G4200DError<DI::Error>> {
+        let rate = self.read_rate_dps_with(filter)?;
+        self.integrate_rate(rate);
+        Ok(self.integrator.angles_deg())
+    }
+
+    // Feeds the rate of `delta` to `detector`, moving part of it into the drift compensation while
+    // stationary. The detector sees the rate before the scale and misalignment correction, in the
+    // same frame as the compensation. Returns the corrected rate to integrate, zero while holding
+    // the angle
+    fn track_stationary<const N: usize>(&mut self, delta: (i16, i16, i16), detector: &mut StationaryDetector<N>) -> ((f32, f32, f32), Option<MotionState>) {
+        let uncorrected = self.to_uncorrected_dps(delta);
+        let rate = self.apply_correction(uncorrected);
+        let change = detector.update((uncorrected[0], uncorrected[1], uncorrected[2]));
+        if !detector.is_stationary() {
+            return (rate, change);
+        }
+        let options = *detector.options();
+        let digits_per_dps = 1000.0 / self.full_scale.sensitivity_mdps();
+        for (compensation, rate) in self.drift_compensation.iter_mut().zip(uncorrected) {
+            *compensation -= options.bias_gain * rate * digits_per_dps;
+        }
+        if options.hold_angle {
+            ((0.0, 0.0, 0.0), change)
+        } else {
+            (rate, change)
+        }
+    }
+
+    /// Like `read_position`, with `detector` watching the rate. While the device is stationary,
+    /// `bias_gain` of the remaining rate moves into the drift compensation at every sample and,
+    /// with `hold_angle`, the angles and attitude stay where they are. Returns the angles along
+    /// with the new motion state when it changed.
+    pub fn read_position_detecting<const N: usize>(&mut self, detector: &mut StationaryDetector<N>) -> Result<DetectedPosition, L3G4200DError<DI::Error>> {
+        let delta = self.read_delta_for_rate()?;
+        let (rate, change) = self.track_stationary(delta, detector);
+        self.integrate_rate(rate);
+        Ok(DetectedPosition { angles_deg: self.integrator.angles_deg(), change })
+    }
+
+    /// Like `read_position_at`, with `detector` watching the rate as in `read_position_detecting`.
+    pub fn read_position_detecting_at<const N: usize>(&mut self, detector: &mut StationaryDetector<N>, timestamp: f64) -> Result<DetectedPosition, L3G4200DError<DI::Error>> {
+        let delta = self.read_delta_for_rate()?;
+        let ((x, y, z), change) = self.track_stationary(delta, detector);
+        self.integrator.update_at((x as f64, y as f64, z as f64), timestamp);
+        self.attitude.update_at((x as f64, y as f64, z as f64), timestamp);
+        Ok(DetectedPosition { angles_deg: self.integrator.angles_deg(), change })
+    }
+
+    pub fn callibrate_drift(&mut self, iterations: i32) -> Result<[i32; 3], L3G4200DError<DI::Error>> {
         let mut drift: [i32; 3] = [0, 0, 0];
         for _ in 0..iterations {
             let (x, y, z) = self.read_raw_delta()?;
@@ -140,20 +614,146 @@ impl L3G4200D {
         drift[1] /= iterations;
         drift[2] /= iterations;
 
-        self.drift_compensation[0] = -1 * (drift[0] as i16);
-        self.drift_compensation[1] = -1 * (drift[1] as i16);
-        self.drift_compensation[2] = -1 * (drift[2] as i16);
+        self.drift_compensation[0] = -(drift[0] as f32);
+        self.drift_compensation[1] = -(drift[1] as f32);
+        self.drift_compensation[2] = -(drift[2] as f32);
 
         Ok(drift)
     }
 
-    pub fn callibrate_temperature(&mut self, current_temperature: Option<i8>) -> Result<i8, L3G4200DError> {
-        self.temp_compensation = (-1 * self.read_temperature()?) + current_temperature.unwrap_or(0);
+    // Statistics over `options.samples` fresh uncompensated samples, checked for motion
+    fn measure_drift<D: DelayNs>(&mut self, delay: &mut D, options: &DriftCalibrationOptions) -> Result<DriftStatistics, L3G4200DError<DI::Error>> {
+        if options.samples < 2 {
+            return Err(L3G4200DError::NotEnoughSamples());
+        }
+        let mut axes = [AxisAccumulator::new(); 3];
+        for _ in 0..options.samples {
+            self.wait_for_data_ready(delay, options.timeout_us)?;
+            let mut data = [0u8; 6];
+            self.read_block_data(L3G4200DRegister::OUT_X_L, &mut data)?;
+            let (x, y, z) = decode_axes(&data, self.byte_order);
+            axes[0].push(x);
+            axes[1].push(y);
+            axes[2].push(z);
+        }
+
+        let statistics = DriftStatistics {
+            x: axes[0].statistics(),
+            y: axes[1].statistics(),
+            z: axes[2].statistics(),
+        };
+        let max_std_dev = options.max_std_dev_dps * 1000.0 / self.full_scale.sensitivity_mdps();
+        if statistics.max_std_dev() > max_std_dev {
+            return Err(L3G4200DError::DeviceMoved());
+        }
+        Ok(statistics)
+    }
+
+    /// Measures the zero-rate level over `options.samples` fresh samples (waiting for each one
+    /// through STATUS_REG) and uses the mean as drift compensation, keeping its fractional part.
+    /// While a temperature model is set, the current temperature becomes its reference.
+    ///
+    /// Fails with `L3G4200DError::DeviceMoved` if the standard deviation of any axis goes above
+    /// `options.max_std_dev_dps`, or with `L3G4200DError::NotEnoughSamples` if `options.samples`
+    /// is below 2, in which case the previous compensation is kept.
+    pub fn calibrate_drift_at_rest<D: DelayNs>(&mut self, delay: &mut D, options: &DriftCalibrationOptions) -> Result<DriftStatistics, L3G4200DError<DI::Error>> {
+        let statistics = self.measure_drift(delay, options)?;
+        if self.has_temperature_model() {
+            let raw = self.read_raw_temperature_delta()?;
+            self.track_temperature(raw);
+            self.temperature_reference = from_twos_u8(raw) as f32;
+        }
+        self.drift_compensation = statistics.mean().map(|mean| -mean);
+        Ok(statistics)
+    }
+
+    /// Measures the zero-rate level like `calibrate_drift_at_rest`, along with the OUT_TEMP
+    /// reading, without changing the compensation. Collect these over a warm-up run and pass them to
+    /// `calibration::fit_temperature_model`.
+    pub fn measure_temperature_bias<D: DelayNs>(&mut self, delay: &mut D, options: &DriftCalibrationOptions) -> Result<TemperatureSample, L3G4200DError<DI::Error>> {
+        let statistics = self.measure_drift(delay, options)?;
+        let raw = self.read_raw_temperature_delta()?;
+        self.track_temperature(raw);
+        let temperature = from_twos_u8(raw) as f32;
+        let dps_per_digit = self.full_scale.sensitivity_mdps() / 1000.0;
+        Ok(TemperatureSample { temperature, bias_dps: statistics.mean().map(|mean| mean * dps_per_digit) })
+    }
+
+    // Raw output averaged over `samples` fresh samples, after dropping `settle` of them
+    fn average_output<D: DelayNs>(&mut self, delay: &mut D, options: &SelfTestOptions) -> Result<[f32; 3], L3G4200DError<DI::Error>> {
+        if options.samples == 0 {
+            return Err(L3G4200DError::NotEnoughSamples());
+        }
+        let mut data = [0u8; 6];
+        for _ in 0..options.settle_samples {
+            self.wait_for_data_ready(delay, options.timeout_us)?;
+            self.read_block_data(L3G4200DRegister::OUT_X_L, &mut data)?;
+        }
+        let mut axes = [AxisAccumulator::new(); 3];
+        for _ in 0..options.samples {
+            self.wait_for_data_ready(delay, options.timeout_us)?;
+            self.read_block_data(L3G4200DRegister::OUT_X_L, &mut data)?;
+            let (x, y, z) = decode_axes(&data, self.byte_order);
+            axes[0].push(x);
+            axes[1].push(y);
+            axes[2].push(z);
+        }
+        Ok(axes.map(|axis| axis.statistics().mean * self.full_scale.sensitivity_mdps() / 1000.0))
+    }
+
+    fn run_self_test<D: DelayNs>(&mut self, delay: &mut D, options: &SelfTestOptions, saved: &[u8; 5]) -> Result<SelfTestReport, L3G4200DError<DI::Error>> {
+        let mut ctrl_reg1 = config::ctrlreg1::Value::from(saved[0]);
+        ctrl_reg1.power_down_mode_enable = config::ctrlreg1::PowerDownMode::NORMAL_MODE;
+        (ctrl_reg1.x_enable, ctrl_reg1.y_enable, ctrl_reg1.z_enable) = (true, true, true);
+        self.write_config_reg_1(ctrl_reg1)?;
+
+        let mut outputs = [[0.0; 3]; 3];
+        for (output, mode) in outputs.iter_mut().zip([SelfTestEnabled::NORMAL_MODE, SelfTestEnabled::SELF_TEST_0, SelfTestEnabled::SELF_TEST_1]) {
+            let mut ctrl_reg4 = config::ctrlreg4::Value::from(saved[3]);
+            ctrl_reg4.block_data_update = BlockDataUpdate::WAIT_FOR_READING;
+            ctrl_reg4.self_test_enabled = mode;
+            self.write_config_reg_4(ctrl_reg4)?;
+            *output = self.average_output(delay, options)?;
+        }
+
+        let limits_dps = self_test::self_test_limits_dps(self.full_scale, options.tolerance);
+        let [normal, self_test_0, self_test_1] = outputs;
+        let axis = |i: usize| AxisSelfTest::new(normal[i], self_test_0[i], self_test_1[i], limits_dps);
+        Ok(SelfTestReport { full_scale: self.full_scale, limits_dps, x: axis(0), y: axis(1), z: axis(2) })
+    }
 
-        Ok(-1 * self.temp_compensation)
+    /// Runs the datasheet self-test: averages the output in normal mode, then in self-test mode 0
+    /// and 1, and checks that the change on every axis is close to the typical value for the
+    /// active full scale. The device has to be kept still meanwhile.
+    ///
+    /// Fails with `L3G4200DError::NotEnoughSamples` if `options.samples` is 0. The sensor is
+    /// powered on for the test, and CTRL_REG1 to CTRL_REG5 are restored afterwards, even if the
+    /// test fails with an error.
+    pub fn self_test<D: DelayNs>(&mut self, delay: &mut D, options: &SelfTestOptions) -> Result<SelfTestReport, L3G4200DError<DI::Error>> {
+        let mut saved = [0u8; 5];
+        self.read_block_data(L3G4200DRegister::CTRL_REG1, &mut saved)?;
+        let report = self.run_self_test(delay, options, &saved);
+
+        let registers = [L3G4200DRegister::CTRL_REG1, L3G4200DRegister::CTRL_REG2, L3G4200DRegister::CTRL_REG3, L3G4200DRegister::CTRL_REG4, L3G4200DRegister::CTRL_REG5];
+        let restored = registers.into_iter().zip(saved).try_for_each(|(reg, value)| self.write_byte_data(reg, value));
+        let report = report?;
+        restored?;
+        Ok(report)
     }
 
-    pub fn common_setup(&mut self) -> Result <(), L3G4200DError> {
+    /// Sets the offset so that `read_temperature` returns `current_temperature` now. With `None`
+    /// the current temperature reads as 0, which is only a relative reference:
+    /// `read_absolute_temperature` keeps failing until a real one is given.
+    pub fn callibrate_temperature(&mut self, current_temperature: Option<i8>) -> Result<i8, L3G4200DError<DI::Error>> {
+        let raw = self.read_raw_temperature_delta()?;
+        self.temp_compensation = current_temperature.unwrap_or(0).saturating_add(from_twos_u8(raw));
+        self.temperature_referenced = current_temperature.is_some();
+        self.track_temperature(raw);
+
+        Ok(-self.temp_compensation)
+    }
+
+    pub fn common_setup(&mut self) -> Result<(), L3G4200DError<DI::Error>> {
         use crate::config::*;
 
         self.write_config_reg_1(ctrlreg1::Value {
@@ -170,7 +770,80 @@ impl L3G4200D {
         Ok(())
     }
 
-    pub fn who_am_i(&mut self) -> Result<u8, L3G4200DError> {
+    /// Turns the FIFO on (CTRL_REG5 FIFO_EN) in the given mode.
+    pub fn enable_fifo(&mut self, fifo: config::fifo_ctrl::Value) -> Result<(), L3G4200DError<DI::Error>> {
+        self.write_fifo_ctrl(fifo)?;
+        let mut ctrl_reg5 = self.read_config_reg_5()?;
+        ctrl_reg5.fifo_enable = true;
+        self.write_config_reg_5(ctrl_reg5)
+    }
+
+    /// Turns the FIFO off and puts it back in Bypass mode, which also discards its content.
+    pub fn disable_fifo(&mut self) -> Result<(), L3G4200DError<DI::Error>> {
+        let mut ctrl_reg5 = self.read_config_reg_5()?;
+        ctrl_reg5.fifo_enable = false;
+        self.write_config_reg_5(ctrl_reg5)?;
+        self.write_fifo_ctrl(Default::default())
+    }
+
+    /// Reads every sample stored in the FIFO (up to `samples.len()`) in a single burst, returning
+    /// how many were written to `samples`.
+    ///
+    /// While the FIFO is enabled the sub-address rolls back from OUT_Z_H to OUT_X_L, so a burst
+    /// starting at OUT_X_L walks through consecutive samples.
+    pub fn drain_fifo(&mut self, samples: &mut [(i16, i16, i16)]) -> Result<usize, L3G4200DError<DI::Error>> {
+        let count = self.read_fifo_src()?.len().min(samples.len());
+        let mut data = [0u8; 6 * FIFO_DEPTH];
+        let data = &mut data[..6 * count];
+        self.read_block_data(L3G4200DRegister::OUT_X_L, data)?;
+        for (sample, chunk) in samples.iter_mut().zip(data.chunks_exact(6)) {
+            *sample = self.decode_delta(chunk);
+        }
+        Ok(count)
+    }
+
+    /// Sets the INT1 thresholds in degrees per second, converted with the current full scale
+    /// (see `full_scale`).
+    pub fn set_int1_threshold_dps(&mut self, x: f32, y: f32, z: f32) -> Result<(), L3G4200DError<DI::Error>> {
+        let value = config::int1_ths::Value::from_dps(x, y, z, &self.full_scale);
+        self.write_int1_threshold(value)
+    }
+
+    /// Sets how long an INT1 event has to last, in seconds, converted with the current output
+    /// data rate (see `data_rate`).
+    pub fn set_int1_duration_seconds(&mut self, wait: bool, seconds: f32) -> Result<(), L3G4200DError<DI::Error>> {
+        let value = config::int1_duration::Value::from_seconds(wait, seconds, &self.data_rate);
+        self.write_int1_duration(value)
+    }
+
+    pub fn write_int1_threshold(&mut self, value: config::int1_ths::Value) -> Result<(), L3G4200DError<DI::Error>> {
+        let registers = [
+            L3G4200DRegister::INT1_THS_XH,
+            L3G4200DRegister::INT1_THS_XL,
+            L3G4200DRegister::INT1_THS_YH,
+            L3G4200DRegister::INT1_THS_YL,
+            L3G4200DRegister::INT1_THS_ZH,
+            L3G4200DRegister::INT1_THS_ZL,
+        ];
+        for (reg, byte) in registers.into_iter().zip(value.to_bytes()) {
+            self.write_byte_data(reg, byte)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_int1_threshold(&mut self) -> Result<config::int1_ths::Value, L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 6];
+        self.read_block_data(L3G4200DRegister::INT1_THS_XH, &mut data)?;
+        Ok(data.into())
+    }
+
+    pub fn read_config(&mut self) -> Result<config::Config, L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 5];
+        self.read_block_data(L3G4200DRegister::CTRL_REG1, &mut data)?;
+        Ok(data.into())
+    }
+
+    pub fn who_am_i(&mut self) -> Result<u8, L3G4200DError<DI::Error>> {
         self.read_byte_data(L3G4200DRegister::WHO_AM_I)
     }
 
@@ -179,31 +852,129 @@ impl L3G4200D {
     write_reg_fn!{write_config_reg_3, L3G4200DRegister::CTRL_REG3, config::ctrlreg3::Value}
     write_reg_fn!{write_config_reg_4, L3G4200DRegister::CTRL_REG4, config::ctrlreg4::Value}
     write_reg_fn!{write_config_reg_5, L3G4200DRegister::CTRL_REG5, config::ctrlreg5::Value}
+    write_reg_fn!{write_fifo_ctrl, L3G4200DRegister::FIFO_CTRL, config::fifo_ctrl::Value}
+    write_reg_fn!{write_int1_cfg, L3G4200DRegister::INT1_CFG, config::int1_cfg::Value}
+    write_reg_fn!{write_int1_duration, L3G4200DRegister::INT1_DURATION, config::int1_duration::Value}
 
     read_reg_fn!{read_config_reg_1, L3G4200DRegister::CTRL_REG1, config::ctrlreg1::Value}
     read_reg_fn!{read_config_reg_2, L3G4200DRegister::CTRL_REG2, config::ctrlreg2::Value}
     read_reg_fn!{read_config_reg_3, L3G4200DRegister::CTRL_REG3, config::ctrlreg3::Value}
     read_reg_fn!{read_config_reg_4, L3G4200DRegister::CTRL_REG4, config::ctrlreg4::Value}
     read_reg_fn!{read_config_reg_5, L3G4200DRegister::CTRL_REG5, config::ctrlreg5::Value}
+    read_reg_fn!{read_fifo_ctrl, L3G4200DRegister::FIFO_CTRL, config::fifo_ctrl::Value}
+    read_reg_fn!{read_fifo_src, L3G4200DRegister::FIFO_SRC, config::fifo_src::Value}
+    read_reg_fn!{read_int1_cfg, L3G4200DRegister::INT1_CFG, config::int1_cfg::Value}
+    read_reg_fn!{read_int1_duration, L3G4200DRegister::INT1_DURATION, config::int1_duration::Value}
+    read_reg_fn!{read_int1_src, L3G4200DRegister::INT1_SRC, config::int1_src::Int1Source}
+    read_reg_fn!{read_status, L3G4200DRegister::STATUS_REG, config::status_reg::Value}
 
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+    use embedded_hal_mock::eh1::spi::{Mock, Transaction};
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    fn spi_write(reg: u8, value: u8) -> Vec<Transaction<u8>> {
+        vec![Transaction::transaction_start(), Transaction::write_vec(vec![reg, value]), Transaction::transaction_end()]
+    }
 
     #[test]
     fn test_from_twos_positive() {
-        assert_eq!(L3G4200D::_from_twos(0x1), 1, "Mathematical error");
+        assert_eq!(from_twos(0x1), 1, "Mathematical error");
     }
 
     #[test]
     fn test_from_twos_negative() {
-        assert_eq!(L3G4200D::_from_twos(0xFFFF), -1, "Mathematical error");
+        assert_eq!(from_twos(0xFFFF), -1, "Mathematical error");
     }
 
     #[test]
     fn test_to_u16() {
-        assert_eq!(L3G4200D::_to_u16(0xAB, 0xCD), 0xCDAB, "Mathematical error");
+        assert_eq!(to_u16(0xAB, 0xCD), 0xCDAB, "Mathematical error");
+    }
+
+    #[test]
+    fn new_spi_3_wire_sets_sim() {
+        let mut spi = Mock::new(&spi_write(0x23, 0x01));
+        let gyro = L3G4200D::new_spi(spi.clone(), SerialInterfaceMode::SPI_3_WIRE);
+        assert!(gyro.is_ok(), "SPI initialization should succeed");
+        spi.done();
+    }
+
+    #[test]
+    fn write_byte_data_keeps_sim_on_spi() {
+        let expectations = [spi_write(0x23, 0x01), spi_write(0x23, 0x21)].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut gyro = L3G4200D::new_spi(spi.clone(), SerialInterfaceMode::SPI_3_WIRE).unwrap();
+        gyro.write_byte_data(L3G4200DRegister::CTRL_REG4, 0x20).expect("Write should succeed");
+        spi.done();
+    }
+
+    #[test]
+    fn common_setup_keeps_sim_on_spi() {
+        let expectations = [spi_write(0x23, 0x01), spi_write(0x20, 0x6F), spi_write(0x23, 0x21)].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut gyro = L3G4200D::new_spi(spi.clone(), SerialInterfaceMode::SPI_3_WIRE).unwrap();
+        gyro.common_setup().expect("Setup should succeed");
+        spi.done();
+    }
+
+    #[test]
+    fn probe_i2c_falls_back_to_sdo_low() {
+        let mut i2c = I2cMock::new(&[
+            I2cTransaction::write_read(L3G4200D_ADDR, vec![0x0F], vec![0x00])
+                .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            I2cTransaction::write_read(L3G4200D_ADDR_SDO_LOW, vec![0x0F], vec![L3G4200D_DEV_ID]),
+        ]);
+        let gyro = L3G4200D::probe_i2c(i2c.clone()).expect("Device should be found at 0x68");
+        assert_eq!(gyro.address(), L3G4200D_ADDR_SDO_LOW);
+        i2c.done();
+    }
+
+    #[test]
+    fn probe_i2c_rejects_unknown_device() {
+        let mut i2c = I2cMock::new(&[
+            I2cTransaction::write_read(L3G4200D_ADDR, vec![0x0F], vec![0x33]),
+            I2cTransaction::write_read(L3G4200D_ADDR_SDO_LOW, vec![0x0F], vec![0x33]),
+        ]);
+        let result = L3G4200D::probe_i2c(i2c.clone());
+        assert!(matches!(result, Err(L3G4200DError::DeviceNotFound())), "Only a matching WHO_AM_I should be accepted");
+        i2c.done();
+    }
+
+    #[test]
+    fn read_raw_delta_is_a_single_burst() {
+        let mut i2c = I2cMock::new(&[
+            I2cTransaction::write_read(L3G4200D_ADDR, vec![0xA8], vec![0x01, 0x00, 0xFF, 0xFF, 0x00, 0x80]),
+        ]);
+        let mut gyro = L3G4200D::new_i2c(i2c.clone());
+        assert_eq!(gyro.read_raw_delta().unwrap(), (1, -1, i16::MIN));
+        i2c.done();
+    }
+
+    #[test]
+    fn read_raw_sample_includes_temperature_and_status() {
+        let mut i2c = I2cMock::new(&[
+            I2cTransaction::write_read(L3G4200D_ADDR, vec![0xA6], vec![0x19, 0x0F, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00]),
+        ]);
+        let mut gyro = L3G4200D::new_i2c(i2c.clone());
+        let sample = gyro.read_raw_sample().unwrap();
+        assert_eq!(sample, RawSample { temperature: 0x19, status: 0x0F.into(), delta: (2, 3, 4) });
+        i2c.done();
+    }
+
+    #[test]
+    fn read_config_is_a_single_burst() {
+        let mut i2c = I2cMock::new(&[
+            I2cTransaction::write_read(L3G4200D_ADDR, vec![0xA0], vec![0x6F, 0x00, 0x00, 0x20, 0x00]),
+        ]);
+        let mut gyro = L3G4200D::new_i2c(i2c.clone());
+        let config = gyro.read_config().unwrap();
+        assert_eq!(config.ctrl_reg1, config::ctrlreg1::Value::from(0x6F));
+        assert_eq!(config.ctrl_reg4.full_scale_select, config::ctrlreg4::FullScaleSelection::FSS_2000_DPS);
+        i2c.done();
     }
 }
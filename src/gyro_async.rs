@@ -0,0 +1,245 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::config;
+use crate::config::ctrlreg4::{BigLittleEndian, FullScaleSelection, SerialInterfaceMode};
+use crate::errors::L3G4200DError;
+use crate::gyro::{decode_axes, with_sim, RawSample, DATA_READY_POLL_US, L3G4200D_ADDR, L3G4200D_ADDR_SDO_LOW, L3G4200D_DEV_ID};
+use crate::interface::{AsyncInterface, I2cInterface, SpiInterface};
+use crate::registers::L3G4200DRegister;
+
+/// Async variant of `gyro::L3G4200D`, sharing the same register codecs from `config`.
+///
+/// It keeps no calibration: rates are only scaled by the full scale, without the drift,
+/// temperature and misalignment compensation `gyro::L3G4200D` applies.
+pub struct L3G4200DAsync<DI> {
+    iface: DI,
+    full_scale: FullScaleSelection,
+    byte_order: BigLittleEndian,
+}
+
+macro_rules! write_reg_fn {
+    ( $f:ident, $reg:expr, $cfg:ty ) => {
+        pub async fn $f(&mut self, value: $cfg) -> Result<(), L3G4200DError<DI::Error>> {
+            self.write_byte_data($reg, value.to_value()).await
+        }
+    };
+}
+
+macro_rules! read_reg_fn {
+    ( $f:ident, $reg:expr, $cfg:ty ) => {
+        pub async fn $f(&mut self) -> Result<$cfg, L3G4200DError<DI::Error>> {
+            self.read_byte_data($reg).await.map(|raw| raw.into())
+        }
+    };
+}
+
+impl<I2C: I2c> L3G4200DAsync<I2cInterface<I2C>> {
+    pub fn new_i2c(i2c: I2C) -> Self {
+        Self::with_interface(I2cInterface::new(i2c))
+    }
+
+    pub fn new_i2c_with_address(i2c: I2C, address: u8) -> Self {
+        Self::with_interface(I2cInterface::with_address(i2c, address))
+    }
+
+    /// Looks for the sensor on both addresses selectable through SDO, returning the first one whose
+    /// WHO_AM_I matches `L3G4200D_DEV_ID`. The bus is dropped if no sensor answers.
+    pub async fn probe_i2c(mut i2c: I2C) -> Result<Self, L3G4200DError<I2C::Error>> {
+        for address in [L3G4200D_ADDR, L3G4200D_ADDR_SDO_LOW] {
+            let mut gyro = Self::new_i2c_with_address(i2c, address);
+            match gyro.who_am_i().await {
+                Ok(L3G4200D_DEV_ID) => return Ok(gyro),
+                _ => i2c = gyro.release(),
+            }
+        }
+        Err(L3G4200DError::DeviceNotFound())
+    }
+
+    pub fn address(&self) -> u8 {
+        self.iface.address()
+    }
+
+    pub fn release(self) -> I2C {
+        self.iface.release()
+    }
+}
+
+impl<SPI: SpiDevice> L3G4200DAsync<SpiInterface<SPI>> {
+    /// Also writes CTRL_REG4 so that SIM matches `mode`, which resets the other CTRL_REG4 fields.
+    pub async fn new_spi(spi: SPI, mode: SerialInterfaceMode) -> Result<Self, L3G4200DError<SPI::Error>> {
+        let mut gyro = Self::with_interface(SpiInterface::new(spi, mode));
+        gyro.write_config_reg_4(Default::default()).await?;
+        Ok(gyro)
+    }
+
+    pub fn release(self) -> SPI {
+        self.iface.release()
+    }
+}
+
+impl<DI: AsyncInterface> L3G4200DAsync<DI> {
+    pub fn with_interface(iface: DI) -> Self {
+        L3G4200DAsync {
+            iface,
+            full_scale: FullScaleSelection::FSS_250_DPS,
+            byte_order: BigLittleEndian::LITTLE_ENDIAN,
+        }
+    }
+
+    /// Writes one register. On a bus that needs a particular SIM setting, CTRL_REG4 is always
+    /// written with it, whatever `value` says.
+    pub async fn write_byte_data(&mut self, reg: L3G4200DRegister, mut value: u8) -> Result<(), L3G4200DError<DI::Error>> {
+        reg.enforce_write_protect(value)?;
+        if reg.reg() == L3G4200DRegister::CTRL_REG4.reg() {
+            value = with_sim(value, self.iface.serial_interface_mode());
+        }
+        self.iface.write_register(reg.reg(), value).await
+            .map_err(L3G4200DError::BusError)?;
+        // Keep decoding and unit conversions in step with CTRL_REG4, however it gets written
+        if reg.reg() == L3G4200DRegister::CTRL_REG4.reg() {
+            let ctrl_reg4 = config::ctrlreg4::Value::from(value);
+            self.full_scale = ctrl_reg4.full_scale_select;
+            self.byte_order = ctrl_reg4.big_little_endian;
+        }
+        Ok(())
+    }
+
+    pub async fn read_byte_data(&mut self, reg: L3G4200DRegister) -> Result<u8, L3G4200DError<DI::Error>> {
+        self.iface.read_register(reg.reg()).await
+            .map_err(L3G4200DError::BusError)
+    }
+
+    /// Reads `buffer.len()` consecutive registers starting at `reg` in one transaction.
+    pub async fn read_block_data(&mut self, reg: L3G4200DRegister, buffer: &mut [u8]) -> Result<(), L3G4200DError<DI::Error>> {
+        self.iface.read_registers(reg.reg(), buffer).await
+            .map_err(L3G4200DError::BusError)
+    }
+
+    pub async fn read_raw_delta(&mut self) -> Result<(i16, i16, i16), L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 6];
+        self.read_block_data(L3G4200DRegister::OUT_X_L, &mut data).await?;
+        Ok(decode_axes(&data, self.byte_order))
+    }
+
+    pub async fn read_raw_sample(&mut self) -> Result<RawSample, L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 8];
+        self.read_block_data(L3G4200DRegister::OUT_TEMP, &mut data).await?;
+        Ok(RawSample {
+            temperature: data[0],
+            status: data[1].into(),
+            delta: decode_axes(&data[2..], self.byte_order),
+        })
+    }
+
+    /// Full scale used to convert readings to physical units. It follows every write to CTRL_REG4
+    /// and starts at the power-on value, 250 dps.
+    pub fn full_scale(&self) -> FullScaleSelection {
+        self.full_scale
+    }
+
+    /// Reads the full scale back from CTRL_REG4, for a sensor that was configured before the
+    /// driver was attached.
+    pub async fn refresh_full_scale(&mut self) -> Result<FullScaleSelection, L3G4200DError<DI::Error>> {
+        self.full_scale = self.read_config_reg_4().await?.full_scale_select;
+        Ok(self.full_scale)
+    }
+
+    /// Byte order the output registers are decoded with. It follows every write to CTRL_REG4 and
+    /// starts at the power-on value, little endian.
+    pub fn byte_order(&self) -> BigLittleEndian {
+        self.byte_order
+    }
+
+    /// Reads the byte order back from CTRL_REG4, for a sensor that was configured before the
+    /// driver was attached.
+    pub async fn refresh_byte_order(&mut self) -> Result<BigLittleEndian, L3G4200DError<DI::Error>> {
+        self.byte_order = self.read_config_reg_4().await?.big_little_endian;
+        Ok(self.byte_order)
+    }
+
+    /// Converts a delta to degrees per second with the full scale alone, uncompensated.
+    pub fn to_dps(&self, delta: (i16, i16, i16)) -> (f32, f32, f32) {
+        let (x, y, z) = delta;
+        (self.full_scale.to_dps(x), self.full_scale.to_dps(y), self.full_scale.to_dps(z))
+    }
+
+    /// Uncompensated angular rate in degrees per second.
+    pub async fn read_rate_dps(&mut self) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        let delta = self.read_raw_delta().await?;
+        Ok(self.to_dps(delta))
+    }
+
+    /// Uncompensated angular rate in radians per second.
+    pub async fn read_rate_rad_s(&mut self) -> Result<(f32, f32, f32), L3G4200DError<DI::Error>> {
+        let (x, y, z) = self.read_rate_dps().await?;
+        Ok((x.to_radians(), y.to_radians(), z.to_radians()))
+    }
+
+    /// Polls STATUS_REG until a new sample is available on all three axes, giving up with
+    /// `L3G4200DError::Timeout` after `timeout_us` microseconds.
+    pub async fn wait_for_data_ready<D: DelayNs>(&mut self, delay: &mut D, timeout_us: u32) -> Result<config::status_reg::Value, L3G4200DError<DI::Error>> {
+        let mut waited_us = 0;
+        loop {
+            let status = self.read_status().await?;
+            if status.zyx_available {
+                return Ok(status);
+            }
+            if waited_us >= timeout_us {
+                return Err(L3G4200DError::Timeout());
+            }
+            delay.delay_us(DATA_READY_POLL_US).await;
+            waited_us += DATA_READY_POLL_US;
+        }
+    }
+
+    /// Waits for a new sample and reads it, so that no sample is ever returned twice. If
+    /// `status.zyx_overrun` is set on the result, at least one sample was lost since the previous
+    /// read.
+    pub async fn read_sample_when_ready<D: DelayNs>(&mut self, delay: &mut D, timeout_us: u32) -> Result<RawSample, L3G4200DError<DI::Error>> {
+        self.wait_for_data_ready(delay, timeout_us).await?;
+        self.read_raw_sample().await
+    }
+
+    pub async fn common_setup(&mut self) -> Result<(), L3G4200DError<DI::Error>> {
+        use crate::config::*;
+
+        self.write_config_reg_1(ctrlreg1::Value {
+            dr_bw: ctrlreg1::DataRateBandwidth::ODR_200_CUT_OFF_50,
+            power_down_mode_enable: ctrlreg1::PowerDownMode::NORMAL_MODE,
+            ..Default::default()
+        }).await?;
+
+        self.write_config_reg_4(ctrlreg4::Value {
+            full_scale_select: ctrlreg4::FullScaleSelection::FSS_2000_DPS,
+            ..Default::default()
+        }).await?;
+
+        Ok(())
+    }
+
+    pub async fn read_config(&mut self) -> Result<config::Config, L3G4200DError<DI::Error>> {
+        let mut data = [0u8; 5];
+        self.read_block_data(L3G4200DRegister::CTRL_REG1, &mut data).await?;
+        Ok(data.into())
+    }
+
+    pub async fn who_am_i(&mut self) -> Result<u8, L3G4200DError<DI::Error>> {
+        self.read_byte_data(L3G4200DRegister::WHO_AM_I).await
+    }
+
+    write_reg_fn!{write_config_reg_1, L3G4200DRegister::CTRL_REG1, config::ctrlreg1::Value}
+    write_reg_fn!{write_config_reg_2, L3G4200DRegister::CTRL_REG2, config::ctrlreg2::Value}
+    write_reg_fn!{write_config_reg_3, L3G4200DRegister::CTRL_REG3, config::ctrlreg3::Value}
+    write_reg_fn!{write_config_reg_4, L3G4200DRegister::CTRL_REG4, config::ctrlreg4::Value}
+    write_reg_fn!{write_config_reg_5, L3G4200DRegister::CTRL_REG5, config::ctrlreg5::Value}
+
+    read_reg_fn!{read_config_reg_1, L3G4200DRegister::CTRL_REG1, config::ctrlreg1::Value}
+    read_reg_fn!{read_config_reg_2, L3G4200DRegister::CTRL_REG2, config::ctrlreg2::Value}
+    read_reg_fn!{read_config_reg_3, L3G4200DRegister::CTRL_REG3, config::ctrlreg3::Value}
+    read_reg_fn!{read_config_reg_4, L3G4200DRegister::CTRL_REG4, config::ctrlreg4::Value}
+    read_reg_fn!{read_config_reg_5, L3G4200DRegister::CTRL_REG5, config::ctrlreg5::Value}
+    read_reg_fn!{read_status, L3G4200DRegister::STATUS_REG, config::status_reg::Value}
+
+}
@@ -0,0 +1,190 @@
+/// Numerical rule used to turn angular rate samples into angles.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum IntegrationMethod {
+    /// Rate of the newest sample times the interval
+    Euler,
+    /// Mean of the two samples bounding the interval
+    #[default]
+    Trapezoidal,
+    /// Simpson's rule over each pair of intervals, which may have different lengths. The second
+    /// interval of a pair replaces the trapezoidal estimate of the first, so angles stay current
+    /// after every sample
+    Simpson,
+}
+
+/// Accumulates angular rate samples (in degrees per second) into angles, in f64.
+///
+/// Samples can be fed with the time elapsed since the previous one (`update`), or with absolute
+/// timestamps (`update_at`). Before the first sample the rate is assumed constant, so the first
+/// `update` integrates it over the whole interval.
+#[derive(Debug, Clone)]
+pub struct Integrator {
+    method: IntegrationMethod,
+    angles: [f64; 3],
+    previous_rate: Option<[f64; 3]>,
+    previous_timestamp: Option<f64>,
+    // First half of a Simpson pair: starting rate, interval and the trapezoid added for it
+    pending: Option<([f64; 3], f64, [f64; 3])>,
+}
+
+fn trapezoid(from: &[f64; 3], to: &[f64; 3], dt: f64) -> [f64; 3] {
+    [0, 1, 2].map(|i| (from[i] + to[i]) / 2.0 * dt)
+}
+
+fn simpson(f0: &[f64; 3], f1: &[f64; 3], f2: &[f64; 3], h0: f64, h1: f64) -> [f64; 3] {
+    let w0 = 2.0 - h1 / h0;
+    let w1 = (h0 + h1) * (h0 + h1) / (h0 * h1);
+    let w2 = 2.0 - h0 / h1;
+    [0, 1, 2].map(|i| (h0 + h1) / 6.0 * (w0 * f0[i] + w1 * f1[i] + w2 * f2[i]))
+}
+
+impl Integrator {
+    pub fn new(method: IntegrationMethod) -> Self {
+        Integrator {
+            method,
+            angles: [0.0; 3],
+            previous_rate: None,
+            previous_timestamp: None,
+            pending: None,
+        }
+    }
+
+    pub fn method(&self) -> IntegrationMethod {
+        self.method
+    }
+
+    /// Switches method, keeping the angles accumulated so far.
+    pub fn set_method(&mut self, method: IntegrationMethod) {
+        self.method = method;
+        self.pending = None;
+    }
+
+    /// Adds a sample taken `dt` seconds after the previous one. Non-positive intervals are
+    /// ignored.
+    pub fn update(&mut self, rate_dps: (f64, f64, f64), dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let rate = [rate_dps.0, rate_dps.1, rate_dps.2];
+        let previous = self.previous_rate.unwrap_or(rate);
+        let increment = match self.method {
+            IntegrationMethod::Euler => rate.map(|r| r * dt),
+            IntegrationMethod::Trapezoidal => trapezoid(&previous, &rate, dt),
+            IntegrationMethod::Simpson => match self.pending.take() {
+                None => {
+                    let increment = trapezoid(&previous, &rate, dt);
+                    self.pending = Some((previous, dt, increment));
+                    increment
+                }
+                Some((start, h0, estimate)) => {
+                    let pair = simpson(&start, &previous, &rate, h0, dt);
+                    [0, 1, 2].map(|i| pair[i] - estimate[i])
+                }
+            },
+        };
+        for (angle, increment) in self.angles.iter_mut().zip(increment) {
+            *angle += increment;
+        }
+        self.previous_rate = Some(rate);
+    }
+
+    /// Adds a sample taken at `timestamp` seconds. The first call only records the sample, since
+    /// there is no interval to integrate over yet. Timestamps that do not move forward are ignored.
+    pub fn update_at(&mut self, rate_dps: (f64, f64, f64), timestamp: f64) {
+        match self.previous_timestamp {
+            Some(previous) if timestamp <= previous => return,
+            Some(previous) => self.update(rate_dps, timestamp - previous),
+            None => self.previous_rate = Some([rate_dps.0, rate_dps.1, rate_dps.2]),
+        }
+        self.previous_timestamp = Some(timestamp);
+    }
+
+    pub fn angles_deg(&self) -> (f64, f64, f64) {
+        (self.angles[0], self.angles[1], self.angles[2])
+    }
+
+    pub fn angles_rad(&self) -> (f64, f64, f64) {
+        (self.angles[0].to_radians(), self.angles[1].to_radians(), self.angles[2].to_radians())
+    }
+
+    /// Zeroes the angles and forgets previous samples.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.method);
+    }
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Self::new(IntegrationMethod::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        assert!((actual.1 - expected.1).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        assert!((actual.2 - expected.2).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    // Rate t^2 over [0, 1] at t = 0.1, 0.2, ...
+    fn integrate_square(method: IntegrationMethod) -> f64 {
+        let mut integrator = Integrator::new(method);
+        for step in 0..=10 {
+            let t = step as f64 / 10.0;
+            integrator.update_at((t * t, 0.0, 0.0), t);
+        }
+        integrator.angles_deg().0
+    }
+
+    #[test]
+    fn constant_rate() {
+        for method in [IntegrationMethod::Euler, IntegrationMethod::Trapezoidal, IntegrationMethod::Simpson] {
+            let mut integrator = Integrator::new(method);
+            for _ in 0..200 {
+                integrator.update((90.0, -45.0, 0.5), 0.005);
+            }
+            assert_close(integrator.angles_deg(), (90.0, -45.0, 0.5));
+        }
+    }
+
+    #[test]
+    fn methods_accuracy() {
+        let exact = 1.0 / 3.0;
+        assert!((integrate_square(IntegrationMethod::Euler) - exact).abs() > 0.04);
+        assert!((integrate_square(IntegrationMethod::Trapezoidal) - exact).abs() < 0.002);
+        assert!((integrate_square(IntegrationMethod::Simpson) - exact).abs() < 1e-12);
+    }
+
+    #[test]
+    fn simpson_uneven_intervals() {
+        let mut integrator = Integrator::new(IntegrationMethod::Simpson);
+        for t in [0.0, 0.1, 0.3, 0.35, 0.6, 1.0] {
+            integrator.update_at((t * t, 0.0, 0.0), t);
+        }
+        // Odd number of intervals: the last one is still a trapezoid
+        let simpson_part = 0.6 * 0.6 * 0.6 / 3.0;
+        let trapezoid_part = (0.36 + 1.0) / 2.0 * 0.4;
+        assert!((integrator.angles_deg().0 - simpson_part - trapezoid_part).abs() < 1e-12);
+    }
+
+    #[test]
+    fn radians_and_reset() {
+        let mut integrator = Integrator::new(IntegrationMethod::Euler);
+        integrator.update((180.0, 0.0, -90.0), 1.0);
+        assert_close(integrator.angles_rad(), (core::f64::consts::PI, 0.0, -core::f64::consts::FRAC_PI_2));
+        integrator.reset();
+        assert_close(integrator.angles_deg(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ignores_non_positive_intervals() {
+        let mut integrator = Integrator::new(IntegrationMethod::Trapezoidal);
+        integrator.update_at((10.0, 0.0, 0.0), 1.0);
+        integrator.update_at((10.0, 0.0, 0.0), 1.0);
+        integrator.update_at((10.0, 0.0, 0.0), 0.5);
+        assert_close(integrator.angles_deg(), (0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,263 @@
+use embedded_hal::i2c::I2c;
+use embedded_hal::spi::{Operation, SpiDevice};
+
+use crate::config::ctrlreg4::SerialInterfaceMode;
+use crate::gyro::L3G4200D_ADDR;
+
+// Setting the MSB of the I2C sub-address makes the sensor auto-increment it
+const I2C_AUTO_INCREMENT: u8 = 0x80;
+
+// Flags carried in the SPI address byte
+const SPI_READ: u8 = 0x80;
+const SPI_MULTI_BYTE: u8 = 0x40;
+const SPI_ADDRESS_MASK: u8 = 0x3F;
+
+/// Raw register access to the sensor, independent of the bus it is wired to.
+pub trait Interface {
+    type Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error>;
+
+    fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error>;
+
+    /// Reads `buffer.len()` consecutive registers starting at `reg`.
+    fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        for (offset, value) in buffer.iter_mut().enumerate() {
+            *value = self.read_register(reg + offset as u8)?;
+        }
+        Ok(())
+    }
+
+    /// SIM setting that CTRL_REG4 must hold for this interface to keep working, if any.
+    fn serial_interface_mode(&self) -> Option<SerialInterfaceMode> {
+        None
+    }
+}
+
+/// Async counterpart of `Interface`, for buses implementing embedded-hal-async.
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait AsyncInterface {
+    type Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error>;
+
+    async fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error>;
+
+    /// Reads `buffer.len()` consecutive registers starting at `reg`.
+    async fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// SIM setting that CTRL_REG4 must hold for this interface to keep working, if any.
+    fn serial_interface_mode(&self) -> Option<SerialInterfaceMode> {
+        None
+    }
+}
+
+/// Talks to the sensor through any embedded-hal 1.0 I2C bus.
+#[derive(Debug)]
+pub struct I2cInterface<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C> I2cInterface<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Self::with_address(i2c, L3G4200D_ADDR)
+    }
+
+    pub fn with_address(i2c: I2C, address: u8) -> Self {
+        I2cInterface { i2c, address }
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C: I2c> Interface for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.i2c.write(self.address, &[reg, value])
+    }
+
+    fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut buffer = [0u8];
+        self.i2c.write_read(self.address, &[reg], &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.write_read(self.address, &[I2C_AUTO_INCREMENT | reg], buffer)
+    }
+}
+
+/// Talks to the sensor through an embedded-hal 1.0 SPI device, wired either as 4-wire or 3-wire.
+#[derive(Debug)]
+pub struct SpiInterface<SPI> {
+    spi: SPI,
+    mode: SerialInterfaceMode,
+}
+
+impl<SPI> SpiInterface<SPI> {
+    pub fn new(spi: SPI, mode: SerialInterfaceMode) -> Self {
+        SpiInterface { spi, mode }
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI: SpiDevice> Interface for SpiInterface<SPI> {
+    type Error = SPI::Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.spi.write(&[reg & SPI_ADDRESS_MASK, value])
+    }
+
+    fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut buffer = [0u8];
+        self.spi.transaction(&mut [
+            Operation::Write(&[SPI_READ | (reg & SPI_ADDRESS_MASK)]),
+            Operation::Read(&mut buffer),
+        ])?;
+        Ok(buffer[0])
+    }
+
+    fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            Operation::Write(&[SPI_READ | SPI_MULTI_BYTE | (reg & SPI_ADDRESS_MASK)]),
+            Operation::Read(buffer),
+        ])
+    }
+
+    fn serial_interface_mode(&self) -> Option<SerialInterfaceMode> {
+        Some(self.mode)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c> AsyncInterface for I2cInterface<I2C> {
+    type Error = I2C::Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.i2c.write(self.address, &[reg, value]).await
+    }
+
+    async fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut buffer = [0u8];
+        self.i2c.write_read(self.address, &[reg], &mut buffer).await?;
+        Ok(buffer[0])
+    }
+
+    async fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.write_read(self.address, &[I2C_AUTO_INCREMENT | reg], buffer).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI: embedded_hal_async::spi::SpiDevice> AsyncInterface for SpiInterface<SPI> {
+    type Error = SPI::Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.spi.write(&[reg & SPI_ADDRESS_MASK, value]).await
+    }
+
+    async fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut buffer = [0u8];
+        self.spi.transaction(&mut [
+            Operation::Write(&[SPI_READ | (reg & SPI_ADDRESS_MASK)]),
+            Operation::Read(&mut buffer),
+        ]).await?;
+        Ok(buffer[0])
+    }
+
+    async fn read_registers(&mut self, reg: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            Operation::Write(&[SPI_READ | SPI_MULTI_BYTE | (reg & SPI_ADDRESS_MASK)]),
+            Operation::Read(buffer),
+        ]).await
+    }
+
+    fn serial_interface_mode(&self) -> Option<SerialInterfaceMode> {
+        Some(self.mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+    #[test]
+    fn write_register() {
+        let mut i2c = Mock::new(&[Transaction::write(L3G4200D_ADDR, vec![0x20, 0x0F])]);
+        let mut interface = I2cInterface::new(i2c.clone());
+        interface.write_register(0x20, 0x0F).expect("Write should succeed");
+        i2c.done();
+    }
+
+    #[test]
+    fn read_register() {
+        let mut i2c = Mock::new(&[Transaction::write_read(L3G4200D_ADDR, vec![0x0F], vec![0xD3])]);
+        let mut interface = I2cInterface::new(i2c.clone());
+        assert_eq!(interface.read_register(0x0F).expect("Read should succeed"), 0xD3);
+        i2c.done();
+    }
+
+    #[test]
+    fn read_registers_sets_auto_increment() {
+        let mut i2c = Mock::new(&[Transaction::write_read(L3G4200D_ADDR, vec![0xA8], vec![1, 2, 3, 4, 5, 6])]);
+        let mut interface = I2cInterface::new(i2c.clone());
+        let mut buffer = [0u8; 6];
+        interface.read_registers(0x28, &mut buffer).expect("Read should succeed");
+        assert_eq!(buffer, [1, 2, 3, 4, 5, 6]);
+        i2c.done();
+    }
+
+    #[test]
+    fn spi_write_register() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![0x20, 0x0F]),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut interface = SpiInterface::new(spi.clone(), SerialInterfaceMode::SPI_4_WIRE);
+        interface.write_register(0x20, 0x0F).expect("Write should succeed");
+        spi.done();
+    }
+
+    #[test]
+    fn spi_read_register_sets_read_bit() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![0x8F]),
+            SpiTransaction::read_vec(vec![0xD3]),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut interface = SpiInterface::new(spi.clone(), SerialInterfaceMode::SPI_4_WIRE);
+        assert_eq!(interface.read_register(0x0F).expect("Read should succeed"), 0xD3);
+        spi.done();
+    }
+
+    #[test]
+    fn spi_read_registers_sets_multi_byte_bit() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![0xE8]),
+            SpiTransaction::read_vec(vec![1, 2, 3, 4, 5, 6]),
+            SpiTransaction::transaction_end(),
+        ]);
+        let mut interface = SpiInterface::new(spi.clone(), SerialInterfaceMode::SPI_3_WIRE);
+        let mut buffer = [0u8; 6];
+        interface.read_registers(0x28, &mut buffer).expect("Read should succeed");
+        assert_eq!(buffer, [1, 2, 3, 4, 5, 6]);
+        spi.done();
+    }
+}
@@ -1,4 +1,22 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
 pub mod registers;
 pub mod errors;
 pub mod config;
+pub mod interface;
+pub mod allan;
+pub mod attitude;
+pub mod calibration;
 pub mod gyro;
+pub mod filter;
+pub mod fusion;
+pub mod integrator;
+pub mod self_test;
+pub mod stationary;
+pub mod temperature;
+#[cfg(feature = "async")]
+pub mod gyro_async;
+#[cfg(feature = "std")]
+pub mod emulator;
+#[cfg(feature = "linux")]
+pub mod linux;
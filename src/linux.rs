@@ -0,0 +1,197 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use embedded_hal::i2c::{self, ErrorKind, ErrorType, NoAcknowledgeSource, Operation};
+use embedded_hal::spi;
+use i2cdev::core::{I2CMessage, I2CTransfer};
+use i2cdev::linux::{LinuxI2CBus, LinuxI2CError, LinuxI2CMessage};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+
+use crate::config::ctrlreg4::SerialInterfaceMode;
+use crate::errors::L3G4200DError;
+use crate::gyro::{L3G4200D, L3G4200D_ADDR};
+use crate::interface::{I2cInterface, SpiInterface};
+
+// Errno values the kernel I2C drivers use to report a missing ACK
+const ENXIO: i32 = 6;
+const EREMOTEIO: i32 = 121;
+
+// The datasheet allows SPC up to 10 MHz
+const SPI_MAX_SPEED_HZ: u32 = 10_000_000;
+
+/// `/dev/i2c-N` exposed as an embedded-hal 1.0 I2C bus.
+pub struct LinuxI2c {
+    bus: LinuxI2CBus,
+}
+
+impl LinuxI2c {
+    pub fn new(channel: u8) -> Result<Self, LinuxI2CError> {
+        LinuxI2CBus::new(format!("/dev/i2c-{}", channel)).map(|bus| LinuxI2c { bus })
+    }
+}
+
+#[derive(Debug)]
+pub struct LinuxI2cError(pub LinuxI2CError);
+
+impl Display for LinuxI2cError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for LinuxI2cError {}
+
+impl i2c::Error for LinuxI2cError {
+    fn kind(&self) -> ErrorKind {
+        match self.0 {
+            LinuxI2CError::Errno(ENXIO) | LinuxI2CError::Errno(EREMOTEIO) => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl ErrorType for LinuxI2c {
+    type Error = LinuxI2cError;
+}
+
+impl i2c::I2c for LinuxI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        let mut messages: Vec<LinuxI2CMessage> = operations.iter_mut()
+            .map(|operation| match operation {
+                Operation::Read(buffer) => LinuxI2CMessage::read(buffer),
+                Operation::Write(buffer) => LinuxI2CMessage::write(buffer),
+            }.with_address(address as u16))
+            .collect();
+        self.bus.transfer(&mut messages)
+            .map(|_| ())
+            .map_err(LinuxI2cError)
+    }
+}
+
+impl L3G4200D<I2cInterface<LinuxI2c>> {
+    pub fn new(channel: u8) -> Self {
+        Self::new_safe(channel).expect("Failed to open I2C device")
+    }
+
+    pub fn new_safe(channel: u8) -> Result<Self, String> {
+        Self::new_safe_with_address(channel, L3G4200D_ADDR)
+    }
+
+    pub fn new_with_address(channel: u8, address: u8) -> Self {
+        Self::new_safe_with_address(channel, address).expect("Failed to open I2C device")
+    }
+
+    pub fn new_safe_with_address(channel: u8, address: u8) -> Result<Self, String> {
+        if let Ok(bus) = LinuxI2c::new(channel) {
+            Ok(L3G4200D::new_i2c_with_address(bus, address))
+        } else {
+            Err("Failed to open I2C device".to_string())
+        }
+    }
+
+    pub fn probe(channel: u8) -> Result<Self, L3G4200DError<LinuxI2cError>> {
+        let bus = LinuxI2c::new(channel).map_err(|e| L3G4200DError::BusError(LinuxI2cError(e)))?;
+        L3G4200D::probe_i2c(bus)
+    }
+}
+
+/// `/dev/spidevB.C` exposed as an embedded-hal 1.0 SPI device.
+pub struct LinuxSpi {
+    spi: Spidev,
+}
+
+impl LinuxSpi {
+    pub fn new(bus: u8, chip_select: u8, mode: SerialInterfaceMode) -> Result<Self, io::Error> {
+        let mut spi = Spidev::open(format!("/dev/spidev{}.{}", bus, chip_select))?;
+        // The sensor samples on the rising edge with the clock idling high (SPI mode 3)
+        let mut flags = SpiModeFlags::SPI_MODE_3;
+        if mode == SerialInterfaceMode::SPI_3_WIRE {
+            flags |= SpiModeFlags::SPI_3WIRE;
+        }
+        spi.configure(&SpidevOptions::new()
+            .bits_per_word(8)
+            .max_speed_hz(SPI_MAX_SPEED_HZ)
+            .mode(flags)
+            .build())?;
+        Ok(LinuxSpi { spi })
+    }
+}
+
+#[derive(Debug)]
+pub struct LinuxSpiError(pub io::Error);
+
+impl Display for LinuxSpiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for LinuxSpiError {}
+
+impl spi::Error for LinuxSpiError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl spi::ErrorType for LinuxSpi {
+    type Error = LinuxSpiError;
+}
+
+impl spi::SpiDevice for LinuxSpi {
+    fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        // Every operation goes into a single ioctl so that chip select stays asserted throughout,
+        // which means the transmit and receive buffers have to be laid out up front
+        let mut tx: Vec<Vec<u8>> = Vec::with_capacity(operations.len());
+        let mut rx: Vec<Vec<u8>> = Vec::with_capacity(operations.len());
+        for operation in operations.iter() {
+            let (tx_buffer, rx_len) = match operation {
+                spi::Operation::Read(buffer) => (Vec::new(), buffer.len()),
+                spi::Operation::Write(buffer) => (buffer.to_vec(), 0),
+                spi::Operation::Transfer(read, write) => {
+                    let len = read.len().max(write.len());
+                    let mut buffer = write.to_vec();
+                    buffer.resize(len, 0);
+                    (buffer, len)
+                }
+                spi::Operation::TransferInPlace(buffer) => (buffer.to_vec(), buffer.len()),
+                spi::Operation::DelayNs(_) => (Vec::new(), 0),
+            };
+            tx.push(tx_buffer);
+            rx.push(vec![0; rx_len]);
+        }
+
+        let mut transfers: Vec<SpidevTransfer> = operations.iter()
+            .zip(tx.iter())
+            .zip(rx.iter_mut())
+            .map(|((operation, tx_buffer), rx_buffer)| match operation {
+                spi::Operation::Read(_) => SpidevTransfer::read(rx_buffer),
+                spi::Operation::Write(_) => SpidevTransfer::write(tx_buffer),
+                spi::Operation::Transfer(_, _) | spi::Operation::TransferInPlace(_) => SpidevTransfer::read_write(tx_buffer, rx_buffer),
+                spi::Operation::DelayNs(ns) => SpidevTransfer::delay(ns.div_ceil(1000).min(u16::MAX as u32) as u16),
+            })
+            .collect();
+        self.spi.transfer_multiple(&mut transfers).map_err(LinuxSpiError)?;
+        drop(transfers);
+
+        for (operation, rx_buffer) in operations.iter_mut().zip(rx) {
+            match operation {
+                spi::Operation::Read(buffer) | spi::Operation::TransferInPlace(buffer) => buffer.copy_from_slice(&rx_buffer),
+                spi::Operation::Transfer(read, _) => {
+                    let len = read.len();
+                    read.copy_from_slice(&rx_buffer[..len]);
+                }
+                spi::Operation::Write(_) | spi::Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl L3G4200D<SpiInterface<LinuxSpi>> {
+    pub fn new_spidev(bus: u8, chip_select: u8, mode: SerialInterfaceMode) -> Result<Self, L3G4200DError<LinuxSpiError>> {
+        let spi = LinuxSpi::new(bus, chip_select, mode)
+            .map_err(|e| L3G4200DError::BusError(LinuxSpiError(e)))?;
+        L3G4200D::new_spi(spi, mode)
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::{Debug, Display};
+use core::fmt::{Debug, Display};
 
 use crate::errors::L3G4200DError;
 
@@ -11,7 +11,7 @@ pub struct L3G4200DRegister {
 }
 
 impl Display for L3G4200DRegister {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.register)
     }
 }
@@ -52,7 +52,7 @@ impl L3G4200DRegister {
         Self { register, write_protect: 0x0, read_only: false }
     }
 
-    pub fn enforce_write_protect(&self, value: u8) -> Result<(), L3G4200DError> {
+    pub fn enforce_write_protect<E>(&self, value: u8) -> Result<(), L3G4200DError<E>> {
         if self.read_only {
             return Err(L3G4200DError::NotWritableRegister());
         }
@@ -62,7 +62,7 @@ impl L3G4200DRegister {
         Ok(())
     }
 
-    pub fn reg(&self) -> u8 { self.register }
+    pub const fn reg(&self) -> u8 { self.register }
 }
 
 #[cfg(test)]
@@ -73,37 +73,37 @@ mod tests {
 
     #[test]
     fn enforce_write_protect_WHO_AM_I() {
-        assert!(L3G4200DRegister::WHO_AM_I.enforce_write_protect(0x0).is_err(), "WHO_AM_I should be write-protected");
+        assert!(L3G4200DRegister::WHO_AM_I.enforce_write_protect::<()>(0x0).is_err(), "WHO_AM_I should be write-protected");
     }
 
     #[test]
     fn enforce_write_protect_OUT_X_L() {
-        assert!(L3G4200DRegister::OUT_X_L.enforce_write_protect(0x0).is_err(), "OUT_X_L should be write-protected")
+        assert!(L3G4200DRegister::OUT_X_L.enforce_write_protect::<()>(0x0).is_err(), "OUT_X_L should be write-protected")
     }
 
     #[test]
     fn enforce_write_protect_OUT_X_H() {
-        assert!(L3G4200DRegister::OUT_X_H.enforce_write_protect(0x0).is_err(), "OUT_X_H should be write-protected")
+        assert!(L3G4200DRegister::OUT_X_H.enforce_write_protect::<()>(0x0).is_err(), "OUT_X_H should be write-protected")
     }
 
     #[test]
     fn enforce_write_protect_OUT_Y_L() {
-        assert!(L3G4200DRegister::OUT_Y_L.enforce_write_protect(0x0).is_err(), "OUT_Y_L should be write-protected")
+        assert!(L3G4200DRegister::OUT_Y_L.enforce_write_protect::<()>(0x0).is_err(), "OUT_Y_L should be write-protected")
     }
 
     #[test]
     fn enforce_write_protect_OUT_Y_H() {
-        assert!(L3G4200DRegister::OUT_Y_H.enforce_write_protect(0x0).is_err(), "OUT_Y_H should be write-protected")
+        assert!(L3G4200DRegister::OUT_Y_H.enforce_write_protect::<()>(0x0).is_err(), "OUT_Y_H should be write-protected")
     }
 
     #[test]
     fn enforce_write_protect_OUT_Z_L() {
-        assert!(L3G4200DRegister::OUT_Z_L.enforce_write_protect(0x0).is_err(), "OUT_Z_L should be write-protected")
+        assert!(L3G4200DRegister::OUT_Z_L.enforce_write_protect::<()>(0x0).is_err(), "OUT_Z_L should be write-protected")
     }
 
     #[test]
     fn enforce_write_protect_OUT_Z_H() {
-        assert!(L3G4200DRegister::OUT_Z_H.enforce_write_protect(0x0).is_err(), "OUT_Z_H should be write-protected")
+        assert!(L3G4200DRegister::OUT_Z_H.enforce_write_protect::<()>(0x0).is_err(), "OUT_Z_H should be write-protected")
     }
 
 }
\ No newline at end of file
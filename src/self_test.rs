@@ -0,0 +1,107 @@
+use crate::config::ctrlreg4::FullScaleSelection;
+
+/// Settings for `L3G4200D::self_test`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SelfTestOptions {
+    /// Samples averaged in each mode, at least one
+    pub samples: u32,
+    /// Samples thrown away after switching mode, while the output settles
+    pub settle_samples: u32,
+    /// How long to wait for each sample before giving up
+    pub timeout_us: u32,
+    /// Accepted deviation from the typical output change, as a fraction of it. The datasheet only
+    /// gives typical values (130, 200 and 530 dps at 250, 500 and 2000 dps full scale)
+    pub tolerance: f32,
+}
+
+impl Default for SelfTestOptions {
+    fn default() -> Self {
+        SelfTestOptions {
+            samples: 50,
+            settle_samples: 10,
+            timeout_us: 100_000,
+            tolerance: 0.5,
+        }
+    }
+}
+
+/// Smallest and largest accepted output change, in dps, for `full_scale`.
+pub fn self_test_limits_dps(full_scale: FullScaleSelection, tolerance: f32) -> (f32, f32) {
+    let typical = full_scale.self_test_output_change_dps();
+    (typical * (1.0 - tolerance), typical * (1.0 + tolerance))
+}
+
+/// Outcome of the self-test on one axis.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct AxisSelfTest {
+    /// Average output in normal mode, in dps
+    pub normal_dps: f32,
+    /// Output change from normal mode in self-test mode 0 and 1, in dps
+    pub self_test_0_change_dps: f32,
+    pub self_test_1_change_dps: f32,
+    /// Whether the size of both changes is within the limits, and the two go opposite ways
+    pub passed: bool,
+}
+
+impl AxisSelfTest {
+    pub(crate) fn new(normal_dps: f32, self_test_0_dps: f32, self_test_1_dps: f32, limits_dps: (f32, f32)) -> Self {
+        let self_test_0_change_dps = self_test_0_dps - normal_dps;
+        let self_test_1_change_dps = self_test_1_dps - normal_dps;
+        let within = |change: f32| (limits_dps.0..=limits_dps.1).contains(&change.abs());
+        AxisSelfTest {
+            normal_dps,
+            self_test_0_change_dps,
+            self_test_1_change_dps,
+            passed: within(self_test_0_change_dps)
+                && within(self_test_1_change_dps)
+                && self_test_0_change_dps.signum() != self_test_1_change_dps.signum(),
+        }
+    }
+}
+
+/// Result of `L3G4200D::self_test`, per axis.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SelfTestReport {
+    pub full_scale: FullScaleSelection,
+    pub limits_dps: (f32, f32),
+    pub x: AxisSelfTest,
+    pub y: AxisSelfTest,
+    pub z: AxisSelfTest,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.x.passed && self.y.passed && self.z.passed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_follow_full_scale() {
+        assert_eq!(self_test_limits_dps(FullScaleSelection::FSS_250_DPS, 0.5), (65.0, 195.0));
+        assert_eq!(self_test_limits_dps(FullScaleSelection::FSS_2000_DPS, 0.0), (530.0, 530.0));
+    }
+
+    #[test]
+    fn axis_needs_both_modes_within_limits() {
+        let limits = (65.0, 195.0);
+        let axis = AxisSelfTest::new(1.0, 131.0, -129.0, limits);
+        assert_eq!((axis.self_test_0_change_dps, axis.self_test_1_change_dps), (130.0, -130.0));
+        assert!(axis.passed);
+        assert!(!AxisSelfTest::new(1.0, 131.0, 1.5, limits).passed);
+        assert!(!AxisSelfTest::new(0.0, 250.0, -130.0, limits).passed);
+    }
+
+    #[test]
+    fn axis_needs_opposite_changes() {
+        let limits = (65.0, 195.0);
+        let axis = AxisSelfTest::new(1.0, 131.0, 121.0, limits);
+        assert_eq!((axis.self_test_0_change_dps, axis.self_test_1_change_dps), (130.0, 120.0));
+        assert!(!axis.passed);
+        assert!(!AxisSelfTest::new(1.0, -129.0, -119.0, limits).passed);
+        assert!(AxisSelfTest::new(1.0, -129.0, 121.0, limits).passed);
+    }
+}
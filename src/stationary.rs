@@ -0,0 +1,179 @@
+use libm::sqrtf;
+
+/// Thresholds and behaviour of a `StationaryDetector`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct StationaryOptions {
+    /// Largest rate, on any axis and any sample of the window, still considered as being at rest.
+    /// It has to stay above the uncompensated bias, or the device never looks stationary
+    pub max_rate_dps: f32,
+    /// Largest standard deviation over the window, on any axis, still considered as being at rest
+    pub max_std_dev_dps: f32,
+    /// Fraction of the remaining rate moved into the drift compensation at every stationary
+    /// sample. The bias estimate follows with a time constant of `1 / bias_gain` samples
+    pub bias_gain: f32,
+    /// Stop integrating while stationary, so that noise and leftover bias do not move the angles
+    pub hold_angle: bool,
+}
+
+impl Default for StationaryOptions {
+    fn default() -> Self {
+        StationaryOptions {
+            max_rate_dps: 5.0,
+            max_std_dev_dps: 1.0,
+            bias_gain: 0.001,
+            hold_angle: false,
+        }
+    }
+}
+
+/// Whether the device is at rest, as reported on every change by `StationaryDetector::update`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MotionState {
+    Stationary,
+    Moving,
+}
+
+/// Result of `L3G4200D::read_position_detecting` and `read_position_detecting_at`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DetectedPosition {
+    /// Accumulated angles in degrees
+    pub angles_deg: (f64, f64, f64),
+    /// New motion state, when it changed with this sample
+    pub change: Option<MotionState>,
+}
+
+/// Zero-velocity detector over the last `N` rates (in degrees per second). The device is
+/// stationary once a full window stays below both the rate and the standard deviation
+/// thresholds, and moving again as soon as it does not.
+///
+/// Rotations slower than `max_rate_dps` and smooth enough to pass the deviation check look like
+/// bias, so keep the thresholds tight where slow turns matter.
+#[derive(Debug, Clone)]
+pub struct StationaryDetector<const N: usize> {
+    options: StationaryOptions,
+    window: [[f32; 3]; N],
+    next: usize,
+    count: usize,
+    state: MotionState,
+}
+
+impl<const N: usize> StationaryDetector<N> {
+    pub fn new(options: StationaryOptions) -> Self {
+        const { assert!(N > 0, "StationaryDetector needs a window of at least one sample") };
+        StationaryDetector { options, window: [[0.0; 3]; N], next: 0, count: 0, state: MotionState::Moving }
+    }
+
+    pub fn options(&self) -> &StationaryOptions {
+        &self.options
+    }
+
+    pub fn set_options(&mut self, options: StationaryOptions) {
+        self.options = options;
+    }
+
+    pub fn state(&self) -> MotionState {
+        self.state
+    }
+
+    pub fn is_stationary(&self) -> bool {
+        self.state == MotionState::Stationary
+    }
+
+    /// Adds a rate sample, returning the new state when it changed.
+    pub fn update(&mut self, rate_dps: (f32, f32, f32)) -> Option<MotionState> {
+        self.window[self.next] = [rate_dps.0, rate_dps.1, rate_dps.2];
+        self.next = (self.next + 1) % N;
+        self.count = (self.count + 1).min(N);
+
+        let state = if self.count == N && self.at_rest() { MotionState::Stationary } else { MotionState::Moving };
+        if state == self.state {
+            return None;
+        }
+        self.state = state;
+        Some(state)
+    }
+
+    /// Mean rate over the window, in degrees per second.
+    pub fn mean_dps(&self) -> [f32; 3] {
+        let window = &self.window[..self.count];
+        [0, 1, 2].map(|axis| window.iter().map(|sample| sample[axis]).sum::<f32>() / self.count.max(1) as f32)
+    }
+
+    /// Sample standard deviation over the window, in degrees per second.
+    pub fn std_dev_dps(&self) -> [f32; 3] {
+        if self.count < 2 {
+            return [0.0; 3];
+        }
+        let mean = self.mean_dps();
+        let window = &self.window[..self.count];
+        [0, 1, 2].map(|axis| {
+            let sum: f32 = window.iter().map(|sample| (sample[axis] - mean[axis]) * (sample[axis] - mean[axis])).sum();
+            sqrtf(sum / (self.count - 1) as f32)
+        })
+    }
+
+    fn at_rest(&self) -> bool {
+        let slow = self.window.iter().flatten().all(|rate| rate.abs() <= self.options.max_rate_dps);
+        slow && self.std_dev_dps().iter().all(|&std_dev| std_dev <= self.options.max_std_dev_dps)
+    }
+
+    /// Forgets the window and goes back to moving, without reporting it.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.options);
+    }
+}
+
+impl<const N: usize> Default for StationaryDetector<N> {
+    fn default() -> Self {
+        Self::new(StationaryOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stationary_once_the_window_is_full() {
+        let mut detector = StationaryDetector::<10>::default();
+        for _ in 0..9 {
+            assert_eq!(detector.update((0.3, -0.2, 0.1)), None);
+        }
+        assert_eq!(detector.update((0.3, -0.2, 0.1)), Some(MotionState::Stationary));
+        assert_eq!(detector.update((0.3, -0.2, 0.1)), None);
+        assert!(detector.is_stationary());
+        let mean = detector.mean_dps();
+        assert!((mean[0] - 0.3).abs() < 1e-6 && (mean[1] + 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn moving_on_a_fast_sample() {
+        let mut detector = StationaryDetector::<10>::default();
+        for _ in 0..10 {
+            detector.update((0.0, 0.0, 0.0));
+        }
+        assert_eq!(detector.update((0.0, 0.0, 6.0)), Some(MotionState::Moving));
+        // Back at rest only once the spike leaves the window
+        for _ in 0..9 {
+            assert_eq!(detector.update((0.0, 0.0, 0.0)), None);
+        }
+        assert_eq!(detector.update((0.0, 0.0, 0.0)), Some(MotionState::Stationary));
+    }
+
+    #[test]
+    fn moving_on_vibration() {
+        let mut detector = StationaryDetector::<10>::default();
+        for step in 0..50 {
+            let rate = if step % 2 == 0 { 2.0 } else { -2.0 };
+            assert_eq!(detector.update((rate, 0.0, 0.0)), None);
+        }
+        assert_eq!(detector.state(), MotionState::Moving);
+        assert!(detector.std_dev_dps()[0] > 2.0);
+
+        detector.set_options(StationaryOptions { max_std_dev_dps: 3.0, ..Default::default() });
+        assert_eq!(detector.update((2.0, 0.0, 0.0)), Some(MotionState::Stationary));
+        detector.reset();
+        assert_eq!(detector.state(), MotionState::Moving);
+        assert_eq!(detector.mean_dps(), [0.0; 3]);
+    }
+}
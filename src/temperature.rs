@@ -0,0 +1,72 @@
+/// OUT_TEMP goes down by one digit for every °C the die warms up. The datasheet gives no absolute
+/// zero point, so readings only turn into temperatures once a reference is known.
+pub const TEMPERATURE_SENSITIVITY_DIGITS_PER_C: i8 = -1;
+
+/// Absolute temperature, built from OUT_TEMP and a reference taken from a known thermometer.
+#[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
+pub struct Temperature {
+    celsius: f32,
+}
+
+impl Temperature {
+    pub fn from_celsius(celsius: f32) -> Self {
+        Temperature { celsius }
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f32) -> Self {
+        Temperature { celsius: (fahrenheit - 32.0) / 1.8 }
+    }
+
+    pub fn celsius(&self) -> f32 {
+        self.celsius
+    }
+
+    pub fn fahrenheit(&self) -> f32 {
+        self.celsius * 1.8 + 32.0
+    }
+}
+
+/// Difference between two temperatures, which OUT_TEMP gives without any reference.
+#[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
+pub struct TemperatureChange {
+    celsius: f32,
+}
+
+impl TemperatureChange {
+    pub fn from_celsius(celsius: f32) -> Self {
+        TemperatureChange { celsius }
+    }
+
+    pub(crate) fn from_digits(from: i8, to: i8) -> Self {
+        let digits = to as f32 - from as f32;
+        Self::from_celsius(digits / TEMPERATURE_SENSITIVITY_DIGITS_PER_C as f32)
+    }
+
+    pub fn celsius(&self) -> f32 {
+        self.celsius
+    }
+
+    pub fn fahrenheit(&self) -> f32 {
+        self.celsius * 1.8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_units() {
+        let temperature = Temperature::from_celsius(25.0);
+        assert_eq!(temperature.fahrenheit(), 77.0);
+        assert_eq!(Temperature::from_fahrenheit(-40.0).celsius(), -40.0);
+        assert_eq!(TemperatureChange::from_celsius(10.0).fahrenheit(), 18.0);
+    }
+
+    #[test]
+    fn change_from_digits() {
+        // Warming up makes OUT_TEMP go down
+        assert_eq!(TemperatureChange::from_digits(5, -3).celsius(), 8.0);
+        assert_eq!(TemperatureChange::from_digits(-128, 127).celsius(), -255.0);
+    }
+}
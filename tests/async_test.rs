@@ -0,0 +1,73 @@
+#![cfg(feature = "async")]
+
+use embassy_futures::block_on;
+use embedded_hal_async::delay::DelayNs;
+use l3g4200d::config::ctrlreg4::{FullScaleSelection, SerialInterfaceMode};
+use l3g4200d::emulator::Emulator;
+use l3g4200d::errors::L3G4200DError;
+use l3g4200d::gyro::{L3G4200D_ADDR_SDO_LOW, L3G4200D_DEV_ID};
+use l3g4200d::gyro_async::L3G4200DAsync;
+use l3g4200d::registers::L3G4200DRegister;
+
+struct NoDelay;
+
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[test]
+fn who_am_i() {
+    let mut gyro = L3G4200DAsync::new_i2c(Emulator::new());
+    assert_eq!(block_on(gyro.who_am_i()).unwrap(), L3G4200D_DEV_ID);
+}
+
+#[test]
+fn probe_sdo_low() {
+    let gyro = block_on(L3G4200DAsync::probe_i2c(Emulator::with_address(L3G4200D_ADDR_SDO_LOW))).expect("Device should be found");
+    assert_eq!(gyro.address(), L3G4200D_ADDR_SDO_LOW);
+}
+
+#[test]
+fn config_round_trip_over_spi() {
+    let emulator = Emulator::new();
+    let mut gyro = block_on(L3G4200DAsync::new_spi(emulator.clone(), SerialInterfaceMode::SPI_3_WIRE)).unwrap();
+    block_on(gyro.common_setup()).unwrap();
+    assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG4), 0x21);
+
+    let config = block_on(gyro.read_config()).unwrap();
+    assert_eq!(config.ctrl_reg4.full_scale_select, FullScaleSelection::FSS_2000_DPS);
+    assert_eq!(config.ctrl_reg4.serial_interface_mode, SerialInterfaceMode::SPI_3_WIRE);
+}
+
+#[test]
+fn wait_for_data_ready_then_read() {
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200DAsync::new_i2c(emulator.clone());
+    block_on(gyro.common_setup()).unwrap();
+
+    emulator.push_sample([5, -6, 7]);
+    block_on(gyro.wait_for_data_ready(&mut NoDelay, 1000)).unwrap();
+    assert_eq!(block_on(gyro.read_raw_delta()).unwrap(), (5, -6, 7));
+}
+
+#[test]
+fn wait_for_data_ready_times_out() {
+    let mut gyro = L3G4200DAsync::new_i2c(Emulator::new());
+    block_on(gyro.common_setup()).unwrap();
+    let result = block_on(gyro.wait_for_data_ready(&mut NoDelay, 1000));
+    assert!(matches!(result, Err(L3G4200DError::Timeout())));
+}
+
+#[test]
+fn decoding_follows_byte_order() {
+    use l3g4200d::config::ctrlreg4::{self, BigLittleEndian};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200DAsync::new_i2c(emulator.clone());
+    block_on(gyro.common_setup()).unwrap();
+    block_on(gyro.write_config_reg_4(ctrlreg4::Value { big_little_endian: BigLittleEndian::BIG_ENDIAN, ..Default::default() })).unwrap();
+
+    emulator.push_samples([[0x1234, -2, 3], [-300, 400, -500]]);
+    assert_eq!(block_on(gyro.read_raw_delta()).unwrap(), (0x1234, -2, 3));
+    assert_eq!(block_on(gyro.read_raw_sample()).unwrap().delta, (-300, 400, -500));
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "linux")]
+
 use l3g4200d::gyro::L3G4200D;
 
 #[test]
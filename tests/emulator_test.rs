@@ -0,0 +1,797 @@
+#![cfg(feature = "std")]
+
+use l3g4200d::config::ctrlreg4::SerialInterfaceMode;
+use l3g4200d::emulator::Emulator;
+use l3g4200d::errors::L3G4200DError;
+use l3g4200d::gyro::{L3G4200D, L3G4200D_ADDR_SDO_LOW, L3G4200D_DEV_ID};
+use l3g4200d::registers::L3G4200DRegister;
+
+#[test]
+fn who_am_i_i2c() {
+    let mut gyro = L3G4200D::new_i2c(Emulator::new());
+    assert_eq!(gyro.who_am_i().unwrap(), L3G4200D_DEV_ID);
+}
+
+#[test]
+fn who_am_i_spi() {
+    let mut gyro = L3G4200D::new_spi(Emulator::new(), SerialInterfaceMode::SPI_4_WIRE).unwrap();
+    assert_eq!(gyro.who_am_i().unwrap(), L3G4200D_DEV_ID);
+}
+
+#[test]
+fn probe_sdo_low() {
+    let gyro = L3G4200D::probe_i2c(Emulator::with_address(L3G4200D_ADDR_SDO_LOW)).expect("Device should be found");
+    assert_eq!(gyro.address(), L3G4200D_ADDR_SDO_LOW);
+}
+
+#[test]
+fn common_setup_writes_registers() {
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG1), 0x6F);
+    assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG4), 0x20);
+    assert!(emulator.ignored_writes().is_empty(), "Setup should only touch writable bits");
+}
+
+#[test]
+fn read_only_register_is_rejected_by_driver() {
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    let result = gyro.write_byte_data(L3G4200DRegister::WHO_AM_I, 0x00);
+    assert!(matches!(result, Err(L3G4200DError::NotWritableRegister())));
+    assert_eq!(emulator.register(L3G4200DRegister::WHO_AM_I), L3G4200D_DEV_ID);
+}
+
+#[test]
+fn read_raw_delta_returns_scripted_samples() {
+    let emulator = Emulator::new();
+    emulator.push_samples([[100, -200, 300], [-1, 0, 1]]);
+    let mut gyro = L3G4200D::new_spi(emulator.clone(), SerialInterfaceMode::SPI_3_WIRE).unwrap();
+    gyro.common_setup().unwrap();
+    assert_eq!(gyro.read_raw_delta().unwrap(), (100, -200, 300));
+    assert_eq!(gyro.read_raw_delta().unwrap(), (-1, 0, 1));
+    assert_eq!(emulator.pending_samples(), 0);
+}
+
+#[test]
+fn drift_calibration_compensates_bias() {
+    let emulator = Emulator::new();
+    emulator.push_samples(std::iter::repeat_n([12, -7, 3], 10));
+    emulator.push_sample([1012, -7, 3]);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    assert_eq!(gyro.callibrate_drift(10).unwrap(), [12, -7, 3]);
+    assert_eq!(gyro.read_raw_delta().unwrap(), (1000, 0, 0));
+}
+
+#[test]
+fn read_position_integrates_samples() {
+    let emulator = Emulator::new();
+    emulator.push_samples(std::iter::repeat_n([2400, 0, -4800], 3));
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    // 2400 digits at 2000 dps is 168 dps, read at 200 Hz
+    gyro.read_position().unwrap();
+    gyro.read_position().unwrap();
+    let (x, y, z) = gyro.read_position().unwrap();
+    assert!((x - 2.52).abs() < 1e-4);
+    assert_eq!(y, 0.0);
+    assert!((z + 5.04).abs() < 1e-4);
+    assert!((gyro.position_rad().0 - 2.52f64.to_radians()).abs() < 1e-4);
+
+    gyro.reset_position();
+    assert_eq!(gyro.position_deg(), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn read_position_at_uses_timestamps() {
+    let emulator = Emulator::new();
+    emulator.push_samples([[1000, 0, 0], [1000, 0, 0], [3000, 0, 0]]);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    // 70 dps, then 70 dps half a second later, then 210 dps one second after that
+    assert_eq!(gyro.read_position_at(10.0).unwrap(), (0.0, 0.0, 0.0));
+    gyro.read_position_at(10.5).unwrap();
+    let (x, _, _) = gyro.read_position_at(11.5).unwrap();
+    assert!((x - (35.0 + 140.0)).abs() < 1e-4);
+}
+
+#[test]
+fn temperature_calibration() {
+    let emulator = Emulator::new();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0x05);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.callibrate_temperature(Some(25)).unwrap();
+
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0x03);
+    assert_eq!(gyro.read_temperature().unwrap(), 27);
+}
+
+#[test]
+fn drain_fifo_in_one_burst() {
+    use l3g4200d::config::fifo_ctrl::{FifoMode, Value};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    gyro.enable_fifo(Value { fifo_mode: FifoMode::STREAM, watermark: 8 }).unwrap();
+    emulator.push_samples((0..10).map(|i| [i, -i, 2 * i]));
+
+    let status = gyro.read_fifo_src().unwrap();
+    assert!(status.watermark);
+    assert_eq!(status.len(), 10);
+
+    let mut samples = [(0, 0, 0); 32];
+    assert_eq!(gyro.drain_fifo(&mut samples).unwrap(), 10);
+    assert_eq!(samples[0], (0, 0, 0));
+    assert_eq!(samples[9], (9, -9, 18));
+    assert!(gyro.read_fifo_src().unwrap().is_empty());
+
+    gyro.disable_fifo().unwrap();
+    assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG5) & 0x40, 0);
+}
+
+#[test]
+fn int1_wake_on_rotation() {
+    use l3g4200d::config::{int1_cfg, int1_duration, int1_ths};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    gyro.write_int1_cfg(int1_cfg::Value {
+        latch_interrupt: true,
+        x_high_enable: true,
+        y_high_enable: true,
+        z_high_enable: true,
+        ..Default::default()
+    }).unwrap();
+    gyro.set_int1_threshold_dps(70.0, 70.0, 700.0).unwrap();
+    gyro.set_int1_duration_seconds(false, 0.05).unwrap();
+
+    assert_eq!(emulator.register(L3G4200DRegister::INT1_CFG), 0x6A);
+    assert_eq!(emulator.register(L3G4200DRegister::INT1_THS_ZH), 0x27);
+    assert_eq!(emulator.register(L3G4200DRegister::INT1_THS_ZL), 0x10);
+    assert_eq!(gyro.read_int1_threshold().unwrap(), int1_ths::Value { x: 1000, y: 1000, z: 10000 });
+    assert_eq!(gyro.read_int1_duration().unwrap(), int1_duration::Value { wait: false, duration: 10 });
+    assert!(emulator.ignored_writes().is_empty());
+
+    emulator.set_register(L3G4200DRegister::INT1_SRC, 0x60);
+    let source = gyro.read_int1_src().unwrap();
+    assert!(source.active && source.z_high);
+    assert!(!source.x_high && !source.z_low);
+}
+
+struct NoDelay;
+
+impl embedded_hal::delay::DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[test]
+fn read_sample_when_ready_never_repeats_a_sample() {
+    let emulator = Emulator::new();
+    emulator.push_samples([[1, 2, 3], [4, 5, 6]]);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    let sample = gyro.read_sample_when_ready(&mut NoDelay, 1000).unwrap();
+    assert_eq!(sample.delta, (1, 2, 3));
+    assert!(!sample.status.zyx_overrun);
+    assert_eq!(gyro.read_sample_when_ready(&mut NoDelay, 1000).unwrap().delta, (4, 5, 6));
+
+    let result = gyro.read_sample_when_ready(&mut NoDelay, 1000);
+    assert!(matches!(result, Err(L3G4200DError::Timeout())));
+}
+
+#[test]
+fn read_sample_when_ready_reports_overrun() {
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples([[1, 1, 1], [2, 2, 2]]);
+    emulator.tick();
+    emulator.tick();
+
+    let status = gyro.read_status().unwrap();
+    assert!(status.zyx_available && status.zyx_overrun);
+    let sample = gyro.read_sample_when_ready(&mut NoDelay, 1000).unwrap();
+    assert_eq!(sample.delta, (2, 2, 2));
+    assert!(sample.status.zyx_overrun);
+    assert!(!gyro.read_status().unwrap().zyx_overrun);
+}
+
+#[test]
+fn rate_follows_full_scale() {
+    use l3g4200d::config::ctrlreg4::{self, FullScaleSelection};
+
+    let emulator = Emulator::new();
+    emulator.push_samples([[1000, -1000, 0], [1000, -1000, 0]]);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    assert_eq!(gyro.full_scale(), FullScaleSelection::FSS_250_DPS);
+    gyro.common_setup().unwrap();
+    assert_eq!(gyro.full_scale(), FullScaleSelection::FSS_2000_DPS);
+    assert_eq!(gyro.read_rate_dps().unwrap(), (70.0, -70.0, 0.0));
+
+    gyro.write_config_reg_4(ctrlreg4::Value { full_scale_select: FullScaleSelection::FSS_500_DPS, ..Default::default() }).unwrap();
+    let (x, y, z) = gyro.read_rate_rad_s().unwrap();
+    assert!((x - 17.5f32.to_radians()).abs() < 1e-6);
+    assert!((y + 17.5f32.to_radians()).abs() < 1e-6);
+    assert_eq!(z, 0.0);
+
+    let mut attached = L3G4200D::new_i2c(emulator.clone());
+    assert_eq!(attached.refresh_full_scale().unwrap(), FullScaleSelection::FSS_500_DPS);
+}
+
+#[test]
+fn attitude_tracks_rotation_about_two_axes() {
+    use l3g4200d::attitude::{EulerOrder, Quaternion};
+
+    // 70 dps for 1.2 s about X, then about the new Y, at 200 Hz
+    let emulator = Emulator::new();
+    emulator.push_samples(std::iter::repeat_n([1000, 0, 0], 240));
+    emulator.push_samples(std::iter::repeat_n([0, 1000, 0], 240));
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    for _ in 0..480 {
+        gyro.read_attitude().unwrap();
+    }
+
+    let (x, y, z) = gyro.attitude().euler_deg(EulerOrder::XYZ);
+    assert!((x - 84.0).abs() < 1e-6 && (y - 84.0).abs() < 1e-6 && z.abs() < 1e-6);
+    // Rotating about Y first would leave the body X axis somewhere else
+    let angle = 84f64.to_radians();
+    let expected = Quaternion::from_euler((angle, angle, 0.0), EulerOrder::XYZ).rotate((1.0, 0.0, 0.0));
+    let swapped = Quaternion::from_euler((angle, angle, 0.0), EulerOrder::YXZ).rotate((1.0, 0.0, 0.0));
+    let actual = gyro.attitude().orientation().rotate((1.0, 0.0, 0.0));
+    assert!((actual.0 - expected.0).abs() < 1e-6 && (actual.1 - expected.1).abs() < 1e-6 && (actual.2 - expected.2).abs() < 1e-6);
+    assert!((actual.1 - swapped.1).abs() > 0.1);
+
+    gyro.attitude_mut().reset();
+    assert_eq!(gyro.attitude().orientation(), Quaternion::IDENTITY);
+}
+
+fn ctrl_reg4_with(byte_order: l3g4200d::config::ctrlreg4::BigLittleEndian) -> l3g4200d::config::ctrlreg4::Value {
+    use l3g4200d::config::ctrlreg4::{self, FullScaleSelection};
+
+    ctrlreg4::Value {
+        full_scale_select: FullScaleSelection::FSS_2000_DPS,
+        big_little_endian: byte_order,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decoding_follows_byte_order() {
+    use l3g4200d::config::ctrlreg4::BigLittleEndian;
+
+    for byte_order in [BigLittleEndian::LITTLE_ENDIAN, BigLittleEndian::BIG_ENDIAN] {
+        let emulator = Emulator::new();
+        let mut gyro = L3G4200D::new_spi(emulator.clone(), SerialInterfaceMode::SPI_4_WIRE).unwrap();
+        gyro.common_setup().unwrap();
+        gyro.write_config_reg_4(ctrl_reg4_with(byte_order)).unwrap();
+        assert_eq!(gyro.byte_order(), byte_order);
+        emulator.push_samples([[0x1234, -2, i16::MIN], [300, -400, 500]]);
+
+        assert_eq!(gyro.read_raw_delta().unwrap(), (0x1234, -2, i16::MIN));
+        assert_eq!(gyro.read_raw_sample().unwrap().delta, (300, -400, 500));
+    }
+}
+
+#[test]
+fn fifo_burst_follows_byte_order() {
+    use l3g4200d::config::ctrlreg4::BigLittleEndian;
+    use l3g4200d::config::fifo_ctrl::{FifoMode, Value};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    gyro.write_config_reg_4(ctrl_reg4_with(BigLittleEndian::BIG_ENDIAN)).unwrap();
+    gyro.enable_fifo(Value { fifo_mode: FifoMode::FIFO, watermark: 0 }).unwrap();
+    emulator.push_samples((0..4).map(|i| [256 * i + 1, -i, 1000 * i]));
+
+    let mut samples = [(0, 0, 0); 32];
+    assert_eq!(gyro.drain_fifo(&mut samples).unwrap(), 4);
+    assert_eq!(samples[3], (769, -3, 3000));
+}
+
+#[test]
+fn byte_order_read_back() {
+    use l3g4200d::config::ctrlreg4::BigLittleEndian;
+
+    let emulator = Emulator::new();
+    emulator.push_sample([0x0102, 0, 0]);
+    L3G4200D::new_i2c(emulator.clone()).write_config_reg_4(ctrl_reg4_with(BigLittleEndian::BIG_ENDIAN)).unwrap();
+    emulator.set_register(L3G4200DRegister::CTRL_REG1, 0x6F);
+
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    assert_eq!(gyro.byte_order(), BigLittleEndian::LITTLE_ENDIAN);
+    assert_eq!(gyro.refresh_byte_order().unwrap(), BigLittleEndian::BIG_ENDIAN);
+    assert_eq!(gyro.read_raw_delta().unwrap(), (0x0102, 0, 0));
+}
+
+#[test]
+fn calibrate_drift_at_rest_keeps_sub_digit_bias() {
+    use l3g4200d::calibration::DriftCalibrationOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples((0..100).map(|i| if i % 2 == 0 { [10, -3, 0] } else { [11, -4, 1] }));
+
+    let options = DriftCalibrationOptions { samples: 100, ..Default::default() };
+    let statistics = gyro.calibrate_drift_at_rest(&mut NoDelay, &options).unwrap();
+    assert_eq!(statistics.mean(), [10.5, -3.5, 0.5]);
+    assert_eq!((statistics.x.min, statistics.x.max, statistics.x.samples), (10, 11, 100));
+    assert!((statistics.y.std_dev - 0.5025).abs() < 1e-4);
+    assert_eq!(emulator.pending_samples(), 0);
+
+    emulator.push_sample([10, -3, 0]);
+    let (x, y, z) = gyro.read_rate_dps().unwrap();
+    assert!((x + 0.035).abs() < 1e-6 && (y - 0.035).abs() < 1e-6 && (z + 0.035).abs() < 1e-6);
+}
+
+#[test]
+fn calibrate_drift_at_rest_rejects_motion() {
+    use l3g4200d::calibration::DriftCalibrationOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples(std::iter::repeat_n([5, 5, 5], 49));
+    // A 35 dps bump
+    emulator.push_sample([505, 5, 5]);
+
+    let options = DriftCalibrationOptions { samples: 50, ..Default::default() };
+    let result = gyro.calibrate_drift_at_rest(&mut NoDelay, &options);
+    assert!(matches!(result, Err(L3G4200DError::DeviceMoved())));
+
+    emulator.push_sample([5, 5, 5]);
+    assert_eq!(gyro.read_raw_delta().unwrap(), (5, 5, 5), "A failed calibration should keep the old compensation");
+}
+
+#[test]
+fn calibrate_drift_at_rest_needs_two_samples() {
+    use l3g4200d::calibration::DriftCalibrationOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples(std::iter::repeat_n([5, 5, 5], 2));
+
+    for samples in [0, 1] {
+        let options = DriftCalibrationOptions { samples, ..Default::default() };
+        let result = gyro.calibrate_drift_at_rest(&mut NoDelay, &options);
+        assert!(matches!(result, Err(L3G4200DError::NotEnoughSamples())));
+    }
+    assert_eq!(emulator.pending_samples(), 2, "Nothing should be read");
+    emulator.push_sample([5, 5, 5]);
+    assert_eq!(gyro.read_raw_delta().unwrap(), (5, 5, 5), "A failed calibration should keep the old compensation");
+}
+
+#[test]
+fn calibrate_drift_at_rest_times_out_without_data() {
+    use l3g4200d::calibration::DriftCalibrationOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples(std::iter::repeat_n([5, 5, 5], 10));
+
+    let result = gyro.calibrate_drift_at_rest(&mut NoDelay, &DriftCalibrationOptions::default());
+    assert!(matches!(result, Err(L3G4200DError::Timeout())));
+}
+
+#[test]
+fn calibration_carries_over_to_a_new_driver() {
+    use l3g4200d::calibration::DriftCalibrationOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.push_samples((0..100).map(|i| if i % 2 == 0 { [10, -3, 0] } else { [11, -4, 1] }));
+    gyro.calibrate_drift_at_rest(&mut NoDelay, &DriftCalibrationOptions { samples: 100, ..Default::default() }).unwrap();
+    let calibration = gyro.keyed_calibration(1);
+    assert!((calibration.bias_dps[0] - 0.735).abs() < 1e-6);
+
+    // After a restart, only the sensor the calibration was made for picks it up
+    let mut other = L3G4200D::new_i2c_with_address(emulator.clone(), L3G4200D_ADDR_SDO_LOW);
+    assert!(!other.apply_calibration_for(1, &calibration));
+    let mut restarted = L3G4200D::new_i2c(emulator.clone());
+    restarted.common_setup().unwrap();
+    assert!(!restarted.apply_calibration_for(2, &calibration));
+    assert!(restarted.apply_calibration_for(1, &calibration));
+    assert_eq!(restarted.keyed_calibration(1), calibration);
+
+    emulator.push_sample([10, -3, 0]);
+    let (x, y, z) = restarted.read_rate_dps().unwrap();
+    assert!((x + 0.035).abs() < 1e-5 && (y - 0.035).abs() < 1e-5 && (z + 0.035).abs() < 1e-5);
+}
+
+#[test]
+fn calibration_corrects_scale_and_misalignment() {
+    use l3g4200d::calibration::Calibration;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    gyro.apply_calibration(&Calibration {
+        bias_dps: [7.0, 0.0, 0.0],
+        correction: [[0.5, 0.0, 0.0], [0.0, 1.0, 0.0], [0.1, 0.0, 1.0]],
+        ..Default::default()
+    });
+
+    // 77 dps on X once the 7 dps bias is removed
+    emulator.push_sample([1200, 0, 0]);
+    let (x, y, z) = gyro.read_rate_dps().unwrap();
+    assert!((x - 38.5).abs() < 1e-4 && y == 0.0 && (z - 7.7).abs() < 1e-4);
+}
+
+#[test]
+fn fitted_correction_restores_full_turns() {
+    use l3g4200d::calibration::{self, ReferenceRotation};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    // Turning by hand through 360° about each axis in 1 s (200 samples at 70 mdps/digit), on a
+    // unit reading 4% high on X with some of the X rotation leaking into Y
+    let turns = [[5349, 72, 0], [0, 5143, 0], [0, 0, -5143]];
+    let mut observations = Vec::new();
+    for (turn, reference) in turns.iter().zip([[360.0, 0.0, 0.0], [0.0, 360.0, 0.0], [0.0, 0.0, -360.0]]) {
+        gyro.reset_position();
+        emulator.push_samples(std::iter::repeat_n(*turn, 200));
+        for _ in 0..200 {
+            gyro.read_position().unwrap();
+        }
+        let (x, y, z) = gyro.position_deg();
+        observations.push(ReferenceRotation { measured: [x as f32, y as f32, z as f32], reference });
+    }
+
+    let fit = calibration::fit_correction(&observations).unwrap();
+    gyro.set_correction(fit.correction);
+    assert_eq!(gyro.correction(), fit.correction);
+    gyro.reset_position();
+    emulator.push_samples(std::iter::repeat_n(turns[0], 200));
+    for _ in 0..200 {
+        gyro.read_position().unwrap();
+    }
+    let (x, y, z) = gyro.position_deg();
+    assert!((x - 360.0).abs() < 0.05 && y.abs() < 0.05 && z.abs() < 0.05, "{:?}", (x, y, z));
+}
+
+#[test]
+fn temperature_model_follows_warm_up() {
+    use l3g4200d::calibration::{self, DriftCalibrationOptions, TemperatureModel};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0);
+    gyro.callibrate_temperature(Some(25)).unwrap();
+
+    // Bias on X grows by one digit (70 mdps) per °C
+    let options = DriftCalibrationOptions { samples: 20, ..Default::default() };
+    let mut samples = Vec::new();
+    for (temperature, raw) in [(20, 5u8), (30, 0xFB), (40, 0xF1)] {
+        emulator.set_register(L3G4200DRegister::OUT_TEMP, raw);
+        emulator.push_samples(std::iter::repeat_n([temperature - 10, -5, 0], 20));
+        let sample = gyro.measure_temperature_bias(&mut NoDelay, &options).unwrap();
+        assert_eq!(sample.temperature, (25 - temperature) as f32);
+        samples.push(sample);
+    }
+    assert_eq!(gyro.calibration().bias_dps, [0.0; 3], "Measuring should not change the compensation");
+
+    let fit = calibration::fit_temperature_model(&samples, TemperatureModel::Linear).unwrap();
+    assert!((fit.coefficients[0][0] - 0.07).abs() < 1e-6);
+    let mut calibration = gyro.calibration();
+    fit.apply_to(&mut calibration);
+    gyro.apply_calibration(&calibration);
+
+    for (temperature, raw) in [(35, 0xF6u8), (22, 3)] {
+        emulator.set_register(L3G4200DRegister::OUT_TEMP, raw);
+        emulator.push_sample([temperature - 10, -5, 0]);
+        let (x, y, z) = gyro.read_rate_dps().unwrap();
+        assert!(x.abs() < 1e-4 && y.abs() < 1e-4 && z.abs() < 1e-4, "{:?} at {} °C", (x, y, z), temperature);
+    }
+
+    // A new temperature offset does not move the model
+    gyro.callibrate_temperature(Some(30)).unwrap();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0xF6);
+    emulator.push_sample([25, -5, 0]);
+    let (x, _, _) = gyro.read_rate_dps().unwrap();
+    assert!(x.abs() < 1e-4, "{}", x);
+
+    // Calibrating again at rest moves the reference, keeping the slope
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0xF1);
+    emulator.push_samples(std::iter::repeat_n([32, -5, 0], 20));
+    gyro.calibrate_drift_at_rest(&mut NoDelay, &options).unwrap();
+    assert_eq!(gyro.calibration().temperature_reference, -15.0);
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0xEC);
+    emulator.push_sample([37, -5, 0]);
+    let (x, _, _) = gyro.read_rate_dps().unwrap();
+    assert!(x.abs() < 1e-4, "{}", x);
+}
+
+#[test]
+fn temperature_needs_a_reference() {
+    use l3g4200d::temperature::Temperature;
+
+    let emulator = Emulator::new();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0x05);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    assert!(matches!(gyro.read_absolute_temperature(), Err(L3G4200DError::NoTemperatureReference())));
+    gyro.callibrate_temperature(None).unwrap();
+    assert!(matches!(gyro.read_absolute_temperature(), Err(L3G4200DError::NoTemperatureReference())));
+
+    gyro.set_temperature_reference(Temperature::from_fahrenheit(71.6)).unwrap();
+    assert_eq!(gyro.read_absolute_temperature().unwrap(), Temperature::from_celsius(22.0));
+    // Calibrating again does not stack offsets
+    gyro.callibrate_temperature(Some(22)).unwrap();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0xFE);
+    let temperature = gyro.read_absolute_temperature().unwrap();
+    assert_eq!(temperature.celsius(), 29.0);
+    assert!((temperature.fahrenheit() - 84.2).abs() < 1e-5);
+
+    let mut restarted = L3G4200D::new_i2c(emulator.clone());
+    restarted.apply_calibration(&gyro.calibration());
+    assert_eq!(restarted.read_absolute_temperature().unwrap().celsius(), 29.0);
+}
+
+#[test]
+fn temperature_change_since_boot() {
+    let emulator = Emulator::new();
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0x10);
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    assert_eq!(gyro.read_temperature_change().unwrap().celsius(), 0.0);
+
+    emulator.set_register(L3G4200DRegister::OUT_TEMP, 0x0A);
+    let change = gyro.read_temperature_change().unwrap();
+    assert_eq!(change.celsius(), 6.0);
+    assert!((change.fahrenheit() - 10.8).abs() < 1e-5);
+    // Does not depend on the reference
+    gyro.callibrate_temperature(Some(40)).unwrap();
+    assert_eq!(gyro.read_temperature_change().unwrap().celsius(), 6.0);
+}
+
+#[test]
+fn self_test_reports_each_axis_and_restores_config() {
+    use l3g4200d::self_test::SelfTestOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    gyro.write_config_reg_3(l3g4200d::config::ctrlreg3::Value { i1_int1: true, ..Default::default() }).unwrap();
+    let before = gyro.read_config().unwrap();
+
+    // 530 dps is 7571 digits at 2000 dps full scale. Z barely moves in self-test mode 1
+    let options = SelfTestOptions { samples: 20, settle_samples: 5, ..Default::default() };
+    for sample in [[10, -20, 5], [7581, 6980, -7566], [-7561, -7020, 300]] {
+        emulator.push_samples(std::iter::repeat_n(sample, 25));
+    }
+    let report = gyro.self_test(&mut NoDelay, &options).unwrap();
+    assert_eq!(emulator.pending_samples(), 0);
+    assert_eq!(report.limits_dps, (265.0, 795.0));
+    assert!((report.x.normal_dps - 0.7).abs() < 1e-4);
+    assert!((report.x.self_test_0_change_dps - 529.97).abs() < 1e-2);
+    assert!((report.y.self_test_1_change_dps + 490.0).abs() < 1e-2);
+    assert!(report.x.passed && report.y.passed);
+    assert!(!report.z.passed && !report.passed());
+
+    assert_eq!(gyro.read_config().unwrap(), before);
+    assert_eq!(gyro.full_scale(), l3g4200d::config::ctrlreg4::FullScaleSelection::FSS_2000_DPS);
+}
+
+#[test]
+fn self_test_restores_config_after_timeout() {
+    use l3g4200d::self_test::SelfTestOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    let before = gyro.read_config().unwrap();
+    emulator.push_samples(std::iter::repeat_n([0, 0, 0], 30));
+    let result = gyro.self_test(&mut NoDelay, &SelfTestOptions::default());
+    assert!(matches!(result, Err(L3G4200DError::Timeout())));
+    assert_eq!(gyro.read_config().unwrap(), before, "The sensor should be powered down again");
+}
+
+#[test]
+fn self_test_keeps_sim_on_spi() {
+    use l3g4200d::config::ctrlreg4::SerialInterfaceMode;
+    use l3g4200d::self_test::SelfTestOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_spi(emulator.clone(), SerialInterfaceMode::SPI_3_WIRE).unwrap();
+    // Restores a CTRL_REG4 read back before the test, with SIM cleared on purpose
+    emulator.set_register(L3G4200DRegister::CTRL_REG4, 0x20);
+    let result = gyro.self_test(&mut NoDelay, &SelfTestOptions::default());
+    assert!(matches!(result, Err(L3G4200DError::Timeout())));
+    assert_eq!(emulator.register(L3G4200DRegister::CTRL_REG4), 0x21);
+}
+
+#[test]
+fn self_test_needs_samples() {
+    use l3g4200d::self_test::SelfTestOptions;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    let before = gyro.read_config().unwrap();
+    emulator.push_samples(std::iter::repeat_n([0, 0, 0], 30));
+    let result = gyro.self_test(&mut NoDelay, &SelfTestOptions { samples: 0, ..Default::default() });
+    assert!(matches!(result, Err(L3G4200DError::NotEnoughSamples())));
+    assert_eq!(gyro.read_config().unwrap(), before);
+}
+
+#[test]
+fn filters_keep_state_between_reads() {
+    use l3g4200d::filter::{Deadband, Filter, Median, MovingAverage};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    // A single 700 dps glitch in a steady 7 dps rotation, plus noise at rest
+    emulator.push_samples([[100, 3, 0], [100, -3, 0], [10000, 3, 0], [100, -3, 0], [100, 3, 0]]);
+    let mut pipeline = Median::<3>::new().then(Deadband::new(0.5));
+    for _ in 0..5 {
+        gyro.read_position_with(&mut pipeline).unwrap();
+    }
+    let (x, y, z) = gyro.position_deg();
+    assert!((x - 0.175).abs() < 1e-6 && y == 0.0 && z == 0.0, "{:?}", (x, y, z));
+
+    emulator.push_samples([[2, 0, 0], [4, 0, 0]]);
+    let mut average = MovingAverage::<4>::new();
+    assert_eq!(gyro.read_delta_with(&mut average).unwrap(), (2.0, 0.0, 0.0));
+    assert_eq!(gyro.read_delta_with(&mut average).unwrap(), (3.0, 0.0, 0.0));
+}
+
+#[test]
+fn complementary_filter_fuses_driver_rates() {
+    use l3g4200d::fusion::complementary::ComplementaryFilter;
+    use l3g4200d::fusion::SyntheticAccelerometer;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    let mut accelerometer = SyntheticAccelerometer::new();
+    accelerometer.set_tilt(20f32.to_radians(), 0.0);
+
+    // Held still at 20° pitch, with 0.7 dps of bias on X
+    emulator.push_samples(std::iter::repeat_n([10, 0, 0], 2000));
+    let mut filter = ComplementaryFilter::new(0.5);
+    for step in 0..2000 {
+        filter.update_from(&mut gyro, &mut accelerometer, step as f64 / 200.0).unwrap();
+    }
+    let (pitch, roll) = filter.angles_deg();
+    assert!((pitch - 20.0).abs() < 0.01 && (roll - 0.35).abs() < 0.02, "{:?}", (pitch, roll));
+}
+
+#[test]
+fn mahony_estimates_driver_bias_with_magnetometer() {
+    use l3g4200d::attitude::{EulerOrder, Quaternion};
+    use l3g4200d::fusion::mahony::Mahony;
+    use l3g4200d::fusion::{Ahrs, SyntheticAccelerometer, SyntheticMagnetometer};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    let truth = Quaternion::from_euler((1.2, -0.3, 0.2), EulerOrder::ZYX);
+    let mut accelerometer = SyntheticAccelerometer::new();
+    let mut magnetometer = SyntheticMagnetometer::new(1.0);
+    accelerometer.set_orientation(&truth);
+    magnetometer.set_orientation(&truth);
+
+    // Held still for a minute, with 0.7 dps of bias on X and -1.4 dps on Z
+    emulator.push_samples(std::iter::repeat_n([10, 0, -20], 12000));
+    let mut filter = Mahony::new(1.0, 0.3);
+    for step in 0..12000 {
+        filter.update_from_marg(&mut gyro, &mut accelerometer, &mut magnetometer, step as f64 / 200.0).unwrap();
+    }
+    let (x, y, z) = filter.gyro_bias_dps();
+    assert!((x - 0.7).abs() < 0.01 && y.abs() < 0.01 && (z + 1.4).abs() < 0.01, "{:?}", (x, y, z));
+    let (yaw, pitch, roll) = filter.euler_rad(EulerOrder::ZYX);
+    assert!((yaw - 1.2).abs() < 1e-3 && (pitch + 0.3).abs() < 1e-3 && (roll - 0.2).abs() < 1e-3, "{:?}", (yaw, pitch, roll));
+}
+
+#[test]
+fn stationary_detection_learns_bias_and_holds_heading() {
+    use l3g4200d::stationary::{MotionState, StationaryDetector, StationaryOptions};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    let options = StationaryOptions { bias_gain: 0.01, hold_angle: true, ..Default::default() };
+    let mut detector = StationaryDetector::<50>::new(options);
+
+    // 10 s at rest with 0.7 dps of bias on Z, a quarter turn, then 10 s at rest again
+    emulator.push_samples(std::iter::repeat_n([0, 0, 10], 2000));
+    emulator.push_samples(std::iter::repeat_n([0, 0, 1296], 200));
+    emulator.push_samples(std::iter::repeat_n([0, 0, 10], 2000));
+    let mut changes = Vec::new();
+    let mut angles = (0.0, 0.0, 0.0);
+    for _ in 0..4200 {
+        let position = gyro.read_position_detecting(&mut detector).unwrap();
+        angles = position.angles_deg;
+        changes.extend(position.change);
+    }
+    assert_eq!(changes, [MotionState::Stationary, MotionState::Moving, MotionState::Stationary]);
+    assert!((gyro.calibration().bias_dps[2] - 0.7).abs() < 0.01, "{:?}", gyro.calibration().bias_dps);
+    // Only the first window, before the detector settles, adds any drift
+    assert!((angles.2 - 90.0).abs() < 0.5, "{:?}", angles);
+}
+
+#[test]
+fn stationary_detection_learns_bias_through_correction() {
+    use l3g4200d::stationary::{StationaryDetector, StationaryOptions};
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    // X and Y mounted a quarter turn apart from the output axes, and Z 20% off scale
+    gyro.set_correction([[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.2]]);
+    let options = StationaryOptions { bias_gain: 0.01, ..Default::default() };
+    let mut detector = StationaryDetector::<50>::new(options);
+
+    emulator.push_samples(std::iter::repeat_n([10, -6, 4], 2000));
+    for _ in 0..2000 {
+        gyro.read_position_detecting(&mut detector).unwrap();
+    }
+    let bias = gyro.calibration().bias_dps;
+    assert!((bias[0] - 0.7).abs() < 0.01 && (bias[1] + 0.42).abs() < 0.01 && (bias[2] - 0.28).abs() < 0.01, "{:?}", bias);
+    emulator.push_samples([[10, -6, 4]]);
+    let (x, y, z) = gyro.read_rate_dps().unwrap();
+    assert!(x.abs() < 0.02 && y.abs() < 0.02 && z.abs() < 0.02, "{:?}", (x, y, z));
+}
+
+#[test]
+fn kalman_filter_learns_driver_bias_from_heading() {
+    use l3g4200d::fusion::kalman::KalmanFilter;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+
+    // Standing still for 30 s with 0.7 dps of bias on Z, and a compass reading 45° at 10 Hz
+    emulator.push_samples(std::iter::repeat_n([0, 0, 10], 6000));
+    let mut filter = KalmanFilter::default();
+    filter.axes_mut()[2].set_angle(45.0, 0.0);
+    for step in 0..6000 {
+        filter.update_from(&mut gyro, step as f64 / 200.0).unwrap();
+        if step % 20 == 0 {
+            filter.correct_heading(45.0, 3.0);
+        }
+    }
+    let heading = &filter.axes()[2];
+    assert!((heading.bias_dps() - 0.7).abs() < 0.05, "{}", heading.bias_dps());
+    assert!((heading.angle_deg() - 45.0).abs() < 0.5 && heading.angle_std_dev_deg() < 1.0);
+    assert!(filter.axes()[0].angle_std_dev_deg() > heading.angle_std_dev_deg());
+}
+
+#[test]
+fn allan_capture_records_settings_and_rates() {
+    use l3g4200d::allan::Capture;
+    use l3g4200d::config::ctrlreg4::FullScaleSelection;
+
+    let emulator = Emulator::new();
+    let mut gyro = L3G4200D::new_i2c(emulator.clone());
+    gyro.common_setup().unwrap();
+    // Alternating by one digit around a 0.7 dps bias
+    emulator.push_samples((0..2000).map(|i| [0, 0, 10 + (i % 2) * 2 - 1]));
+    let capture = Capture::record(&mut gyro, &mut NoDelay, 2000, 10_000).unwrap();
+    assert_eq!(capture.data_rate, gyro.data_rate());
+    assert_eq!(capture.full_scale, FullScaleSelection::FSS_2000_DPS);
+    assert_eq!(capture.rates_dps.len(), 2000);
+
+    let report = Capture::from_log(&capture.to_log()).unwrap().analyze(10);
+    assert_eq!((report.data_rate, report.samples), (gyro.data_rate(), 2000));
+    // Consecutive samples differ by 0.14 dps, and averaging removes it
+    let first = report.z.curve[0];
+    assert_eq!(first.tau_s, 1.0 / gyro.data_rate().output_data_rate_hz() as f64);
+    assert!((first.deviation - 0.14 / 2f64.sqrt()).abs() < 1e-4, "{:?}", first);
+    assert!(report.z.curve.last().unwrap().deviation < 1e-3);
+    assert!(report.x.curve.iter().all(|point| point.deviation == 0.0));
+}